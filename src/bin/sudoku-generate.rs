@@ -0,0 +1,31 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+use std::env;
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sudoku_rustic::generate::{self, PuzzleOptions};
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    let mut options = PuzzleOptions {
+        // Without a given seed, make a new puzzle every time.
+        seed: SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64,
+        ..Default::default()
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value after {}", arg));
+        match arg.as_str() {
+            "--size" => options.elements = value()?.parse()?,
+            "--clues" => options.target_clues = value()?.parse()?,
+            "--seed" => options.seed = value()?.parse()?,
+            "--symmetry" => options.symmetry = value()?.parse()?,
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        }
+    }
+    let puzzle = generate::generate_puzzle(&options)?;
+    eprintln!("Seed: {}", options.seed);
+    print!("{}", sudoku_rustic::format(puzzle));
+    Ok(())
+}
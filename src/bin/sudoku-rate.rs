@@ -0,0 +1,34 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+use std::env;
+use std::error::Error;
+use std::io::{self, Read};
+
+use sudoku_rustic::logic::LogicOptions;
+use sudoku_rustic::rating;
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    let mut options = LogicOptions::default();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--assume-unique" => options.assume_unique = true,
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        }
+    }
+    let mut buffer = String::new();
+    io::stdin().read_to_string(&mut buffer)?;
+    let grid = sudoku_rustic::parse(&buffer)?;
+    let rating = rating::rate_with_options(&grid, options)?;
+    match rating.score {
+        Some(score) => println!("Rating: {:.1} ({})", score, rating.category),
+        None => println!("Rating: unknown ({}), no more logical steps found", rating.category),
+    }
+    if let Some(hardest) = rating.hardest {
+        println!("Hardest technique: {}", hardest);
+    }
+    for (technique, count) in &rating.histogram {
+        println!("{:>5}  {}", count, technique);
+    }
+    Ok(())
+}
@@ -4,24 +4,132 @@
 use std::env;
 use std::error::Error;
 use std::io::{self, Read};
+use std::process;
+
+use sudoku_rustic::logic::LogicOptions;
+use sudoku_rustic::{CellOrder, FormatOptions, SolverOptions, SudokuGrid, Uniqueness};
+
+/// Exit code of `--check-unique` when the puzzle has no solution.
+const EXIT_NO_SOLUTION: i32 = 2;
+
+/// Exit code of `--check-unique` when the puzzle has several solutions.
+const EXIT_MULTIPLE_SOLUTIONS: i32 = 3;
 
 pub fn main() -> Result<(), Box<dyn Error>> {
-    let mut maximum_solutions = 1;
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        maximum_solutions = args[1].parse()?;
+    let mut maximum_solutions = None;
+    let mut options = SolverOptions::default();
+    let mut dancing_links = false;
+    let mut count = false;
+    let mut check_unique = false;
+    let mut explain = false;
+    let mut hint = false;
+    let mut logic_options = LogicOptions::default();
+    let mut format_options = FormatOptions::default();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--fixed-order" => options.cell_order = CellOrder::Fixed,
+            "--dlx" => dancing_links = true,
+            "--count" => count = true,
+            "--check-unique" => check_unique = true,
+            "--explain" => explain = true,
+            "--hint" => hint = true,
+            "--assume-unique" => logic_options.assume_unique = true,
+            "--mark-filled" => format_options.mark_filled = true,
+            _ => match arg.parse() {
+                Ok(maximum) => maximum_solutions = Some(maximum),
+                Err(_) => return Err(format!("Unknown argument {}", arg).into()),
+            },
+        }
+    }
+    let modes = [("--hint", hint), ("--explain", explain), ("--check-unique", check_unique), ("--count", count)];
+    let chosen: Vec<&str> = modes.iter().filter(|&&(_, on)| on).map(|&(flag, _)| flag).collect();
+    if chosen.len() > 1 {
+        return Err(format!("Can't use {} together", chosen.join(" and ")).into());
+    }
+    // What the flags below would have to work with, and if they do.
+    let mode = chosen.first().copied();
+    let with = mode.unwrap_or(if dancing_links { "--dlx" } else { "plain solving" });
+    let logical = matches!(mode, Some("--hint" | "--explain"));
+    let flags = [
+        ("--fixed-order", options.cell_order == CellOrder::Fixed, mode.is_none() && !dancing_links),
+        ("--dlx", dancing_links, matches!(mode, None | Some("--count"))),
+        ("--assume-unique", logic_options.assume_unique, logical),
+        ("--mark-filled", format_options.mark_filled, !matches!(mode, Some("--hint" | "--count"))),
+        ("A maximum number of solutions", maximum_solutions.is_some(), matches!(mode, None | Some("--count"))),
+    ];
+    for (flag, given, works) in flags {
+        if given && !works {
+            return Err(format!("{} has no effect with {}", flag, with).into());
+        }
     }
     let mut buffer = String::new();
     io::stdin().read_to_string(&mut buffer)?;
     let grid = sudoku_rustic::parse(&buffer)?;
-    let solutions = sudoku_rustic::solutions(&grid)?;
-    for (i, solution) in solutions.enumerate().take(maximum_solutions) {
+    if hint {
+        match sudoku_rustic::logic::hint_with_options(&grid, logic_options)? {
+            Some(step) => println!("{}", step),
+            None => println!("No logical step found."),
+        }
+        return Ok(());
+    }
+    if explain {
+        let solution = sudoku_rustic::logic::solve_with_options(&grid, logic_options)?;
+        for step in &solution.steps {
+            println!("{}", step);
+        }
+        if !solution.is_solved() {
+            println!("No more logical steps found.");
+        }
+        println!();
+        print!("{}", sudoku_rustic::format_with_options(solution.grid, format_options));
+        return Ok(());
+    }
+    if check_unique {
+        let uniqueness = sudoku_rustic::check_unique(&grid)?;
+        let ambiguous_cells = uniqueness.ambiguous_cells();
+        match uniqueness {
+            Uniqueness::NoSolution => {
+                println!("No solution.");
+                process::exit(EXIT_NO_SOLUTION);
+            }
+            Uniqueness::Unique(solution) => {
+                println!("Unique solution:");
+                print!("{}", sudoku_rustic::format_with_options(solution, format_options));
+            }
+            Uniqueness::Multiple(first, second) => {
+                println!(
+                    "Multiple solutions, first two differ in {} cells.",
+                    ambiguous_cells.len()
+                );
+                print!("{}", sudoku_rustic::format_with_options(first, format_options));
+                println!("\n == Solution 2 ==");
+                print!("{}", sudoku_rustic::format_with_options(second, format_options));
+                process::exit(EXIT_MULTIPLE_SOLUTIONS);
+            }
+        }
+        return Ok(());
+    }
+    if count {
+        // Without a maximum, count them all.
+        let count = if dancing_links {
+            sudoku_rustic::dlx::count_solutions(&grid, maximum_solutions)?
+        } else {
+            sudoku_rustic::count_solutions(&grid, maximum_solutions)?
+        };
+        println!("{}", count);
+        return Ok(());
+    }
+    let solutions: Box<dyn Iterator<Item = SudokuGrid>> = if dancing_links {
+        Box::new(sudoku_rustic::dlx::solutions(&grid)?)
+    } else {
+        Box::new(sudoku_rustic::solutions_with_options(&grid, options)?)
+    };
+    for (i, solution) in solutions.enumerate().take(maximum_solutions.unwrap_or(1)) {
         if i > 0 {
             println!("\n == Solution {} ==", i + 1);
         }
-        let formatted = sudoku_rustic::format(solution);
+        let formatted = sudoku_rustic::format_with_options(solution, format_options);
         print!("{}", formatted);
     }
     Ok(())
 }
-
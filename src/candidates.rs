@@ -0,0 +1,432 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! A grid where every empty cell keeps the set of elements still
+//! possible in it (its pencil marks), as used by the logical solver.
+//! Grids can be written and read in the pencil-mark layout other sudoku
+//! tools use, to pass on partly solved states.
+
+use crate::{
+    check, element_bit, elements_for_length, format_element, format_header, full_mask, highest_element,
+    load_with_header, mask_elements, parse_element, split_header, ElementType, House, MaskType,
+    SizeType, SudokuError, SudokuGrid, MAX_ELEMENTS,
+};
+
+/// Grids with more elements than this have their candidates written as
+/// numbers separated by commas, as there are only so many single
+/// characters: 1 to 9, A to Z and a to z.
+const MAX_CHAR_CANDIDATES: SizeType = 61;
+
+/// A sudoku grid with candidates for every empty cell.
+#[derive(Clone)]
+pub struct CandidateGrid {
+    // The placed elements, and the shape of the grid.
+    grid: SudokuGrid,
+
+    // Next follows some data at every index ("this cell").
+
+    // Elements still possible in this cell if it is empty, else none.
+    candidates: Vec<MaskType>,
+
+    // Indexes into `houses` of the houses this cell belongs to.
+    cell_houses: Vec<Vec<SizeType>>,
+
+    // The cells of every house, and what each house is.
+    houses: Vec<Vec<SizeType>>,
+    labels: Vec<House>,
+}
+
+impl CandidateGrid {
+    /// Returns a grid with the elements of `grid` placed and every
+    /// element not seen by an empty cell as a candidate in it.
+    pub fn new(grid: &SudokuGrid) -> CandidateGrid {
+        let houses = grid.houses();
+        let labels = grid.house_labels();
+        let mut cell_houses = vec![Vec::new(); grid.size];
+        for (h, house) in houses.iter().enumerate() {
+            for &index in house {
+                cell_houses[index].push(h);
+            }
+        }
+        let candidates = (0..grid.size)
+            .map(|i| match grid.cells[i] {
+                0 => grid.candidates(i),
+                _ => 0,
+            })
+            .collect();
+        CandidateGrid {
+            grid: grid.clone(),
+            candidates,
+            cell_houses,
+            houses,
+            labels,
+        }
+    }
+
+    /// Number of elements, also the number of rows.
+    pub fn elements(&self) -> SizeType {
+        self.grid.elements
+    }
+
+    /// Number of cells.
+    pub fn size(&self) -> SizeType {
+        self.grid.size
+    }
+
+    /// Candidates of the cell at `index` as a mask with bit `v - 1` set
+    /// if element `v` is a candidate; none if the cell is filled.
+    pub fn candidates(&self, index: SizeType) -> MaskType {
+        self.candidates[index]
+    }
+
+    /// True if `value` is a candidate in the cell at `index`.
+    pub fn has(&self, index: SizeType, value: ElementType) -> bool {
+        self.candidates[index] & element_bit(value) != 0
+    }
+
+    /// The cells of every house.
+    pub fn houses(&self) -> &[Vec<SizeType>] {
+        &self.houses
+    }
+
+    /// What each house in `houses` is.
+    pub fn labels(&self) -> &[House] {
+        &self.labels
+    }
+
+    /// True if the two distinct cells share a house.
+    pub fn sees(&self, a: SizeType, b: SizeType) -> bool {
+        a != b
+            && self.cell_houses[a]
+                .iter()
+                .any(|h| self.cell_houses[b].contains(h))
+    }
+
+    /// Cells of house `h` that have `value` as candidate, as a list.
+    pub fn places(&self, h: SizeType, value: ElementType) -> Vec<SizeType> {
+        self.houses[h]
+            .iter()
+            .copied()
+            .filter(|&i| self.has(i, value))
+            .collect()
+    }
+
+    /// True when every cell is filled.
+    pub fn is_solved(&self) -> bool {
+        self.grid.cells.iter().all(|&x| x != 0)
+    }
+
+    /// Places `value` at `index` and removes it from the candidates
+    /// of every cell that sees it. In the other cells of its cage, if
+    /// any, only candidates still adding up to the sum are kept.
+    pub fn place(&mut self, index: SizeType, value: ElementType) {
+        self.grid.cells[index] = value;
+        self.candidates[index] = 0;
+        let bit = element_bit(value);
+        for &h in &self.cell_houses[index] {
+            for &i in &self.houses[h] {
+                self.candidates[i] &= !bit;
+            }
+        }
+        if let Some(cage) = self.grid.cages.iter().find(|cage| cage.cells.contains(&index)) {
+            for &i in &cage.cells {
+                if self.grid.cells[i] == 0 {
+                    self.candidates[i] &= self.grid.cage_fits(cage, i);
+                }
+            }
+        }
+    }
+
+    /// Removes `value` from the candidates at `index`. Returns `false`
+    /// if it wasn't a candidate.
+    pub fn eliminate(&mut self, index: SizeType, value: ElementType) -> bool {
+        let had = self.has(index, value);
+        self.candidates[index] &= !element_bit(value);
+        had
+    }
+
+    /// Puts `value` back as a candidate at `index`. Returns `false` if
+    /// it can't be one: the cell is filled, or a cell it sees holds
+    /// `value`, or `value` is out of range.
+    pub fn restore(&mut self, index: SizeType, value: ElementType) -> bool {
+        let possible =
+            self.grid.cells[index] == 0 && self.grid.candidates(index) & element_bit(value) != 0;
+        if possible {
+            self.candidates[index] |= element_bit(value);
+        }
+        possible
+    }
+
+    /// Columns in a box, normally 3.
+    pub fn box_width(&self) -> SizeType {
+        self.grid.box_width
+    }
+
+    /// Rows in a box, normally 3.
+    pub fn box_height(&self) -> SizeType {
+        self.grid.box_height
+    }
+
+    /// Which box the cell at `index` is in, numbered row by row, or
+    /// which region for grids with irregular regions.
+    pub fn box_of(&self, index: SizeType) -> SizeType {
+        self.grid.region(index)
+    }
+
+    /// The grid of placed elements.
+    pub fn grid(&self) -> &SudokuGrid {
+        &self.grid
+    }
+}
+
+/// Writes the grid in the common pencil-mark layout: filled cells as
+/// their element, empty ones as their candidates, like "1379", in
+/// aligned columns with lines between boxes. Grids with more than 61
+/// elements write numbers instead, and candidates like "1,3,62". An
+/// empty cell without candidates is written as "0". Irregular regions are given by the
+/// region map before the grid, with only a line around the grid.
+pub fn format(grid: &CandidateGrid) -> String {
+    let n = grid.elements();
+    let (width, height) = match grid.grid.regions {
+        Some(_) => (n, n),
+        None => (grid.box_width(), grid.box_height()),
+    };
+    if n == 0 {
+        return String::new();
+    }
+    let texts: Vec<String> = (0..grid.size())
+        .map(|i| match grid.grid.cells[i] {
+            0 if grid.candidates(i) == 0 => "0".to_string(),
+            0 if n > MAX_CHAR_CANDIDATES => mask_elements(grid.candidates(i))
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            0 => mask_elements(grid.candidates(i))
+                .map(format_element)
+                .collect(),
+            value if n > MAX_CHAR_CANDIDATES => value.to_string(),
+            value => format_element(value),
+        })
+        .collect();
+    let widths: Vec<SizeType> = (0..n)
+        .map(|col| {
+            (0..n)
+                .map(|row| texts[row * n + col].len())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let border = |left: char, middle: char, right: char| {
+        let stacks: Vec<String> = widths
+            .chunks(width)
+            .map(|stack| "-".repeat(stack.iter().sum::<SizeType>() + 2 * stack.len()))
+            .collect();
+        format!("{}{}{}\n", left, stacks.join(&middle.to_string()), right)
+    };
+    let mut result = format_header(&grid.grid);
+    result.push_str(&border('.', '.', '.'));
+    for row in 0..n {
+        if row > 0 && row % height == 0 {
+            result.push_str(&border(':', '+', ':'));
+        }
+        for col in 0..n {
+            if col % width == 0 {
+                result.push_str("| ");
+            }
+            result.push_str(&format!(
+                "{:width$}  ",
+                texts[row * n + col],
+                width = widths[col]
+            ));
+            if col % width == width - 1 {
+                // One of the two spaces is the padding before the line.
+                result.pop();
+            }
+        }
+        result.push_str("|\n");
+    }
+    result.push_str(&border('\'', '\'', '\''));
+    result
+}
+
+/// Reads a grid in the pencil-mark layout `format` writes. Each cell is
+/// a word of elements: a single element is a filled cell, more than one
+/// the candidates of an empty cell, so an empty cell with only one
+/// candidate left is read back as filled. With more than 61 elements
+/// the elements of a word are numbers separated by commas. Words are
+/// separated by spaces
+/// and '|'. Words of only '.', '-', ':', '\'', '+', '=' and '*' are
+/// lines and ignored, as are other characters that aren't elements.
+/// Candidates a filled cell sees are dropped.
+pub fn parse(content: &str) -> Result<CandidateGrid, SudokuError> {
+    let (header, content) = split_header(content)?;
+    let words: Vec<&str> = content
+        .split(|c: char| c.is_whitespace() || c == '|')
+        .filter(|word| !word.is_empty() && !word.chars().all(|c| ".-:'+=*".contains(c)))
+        .collect();
+    let numeric = elements_for_length(words.len()).is_ok_and(|n| n > MAX_CHAR_CANDIDATES);
+    let mut values = Vec::with_capacity(words.len());
+    let mut masks = Vec::with_capacity(words.len());
+    for (i, word) in words.iter().enumerate() {
+        let elements: Vec<ElementType> = match numeric {
+            true => word
+                .split(',')
+                .filter(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+                .map(|number| number.parse().unwrap_or(ElementType::MAX))
+                .filter(|&x| x != 0)
+                .collect(),
+            false => word
+                .chars()
+                .filter_map(parse_element)
+                .filter(|&x| x != 0)
+                .collect(),
+        };
+        if let Some(&value) = elements.iter().find(|&&x| SizeType::from(x) > MAX_ELEMENTS) {
+            return Err(SudokuError::ElementOutOfRange { index: i, value });
+        }
+        values.push(if elements.len() == 1 { elements[0] } else { 0 });
+        masks.push(elements.iter().fold(0, |m, &x| m | element_bit(x)));
+    }
+    let grid = load_with_header(&values, &header)?;
+    check(&grid)?;
+    let mut candidates = CandidateGrid::new(&grid);
+    for (i, mask) in masks.into_iter().enumerate() {
+        if mask & !full_mask(grid.elements) != 0 {
+            let value = highest_element(mask);
+            return Err(SudokuError::ElementOutOfRange { index: i, value });
+        }
+        candidates.candidates[i] &= mask;
+    }
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse as parse_grid;
+
+    #[test]
+    fn given_4x4_grid_format_should_write_aligned_pencil_marks() {
+        let grid = CandidateGrid::new(&parse_grid("1... ..3. .... ...4").unwrap());
+        assert_eq!(
+            format(&grid),
+            ".-----------.---------.\n\
+             | 1    234  | 24  2   |\n\
+             | 24   24   | 3   12  |\n\
+             :-----------+---------:\n\
+             | 234  1234 | 12  123 |\n\
+             | 23   123  | 12  4   |\n\
+             '-----------'---------'\n"
+        );
+    }
+
+    #[test]
+    fn given_tall_boxes_format_should_draw_them_and_parse_should_keep_them() {
+        let grid = parse_grid(&format!("boxes 2x3\n12 34 56\n{}", ".".repeat(30))).unwrap();
+        let text = format(&CandidateGrid::new(&grid));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!("boxes 2x3", lines[0]);
+        assert_eq!(3, lines[2].matches('.').count() - 1);
+        assert_eq!(":", &lines[6][..1]);
+        let read = parse(&text).unwrap();
+        assert_eq!((2, 3), (read.box_width(), read.box_height()));
+        assert_eq!(text, format(&read));
+    }
+
+    #[test]
+    fn given_jigsaw_grid_format_should_write_regions_and_parse_should_keep_them() {
+        let grid = parse_grid("regions\nAAAB\nACBB\nCCDB\nCDDD\n1... ..3. .... .2..").unwrap();
+        let grid = CandidateGrid::new(&grid);
+        // r3c4 sees the 3 in its region but not in its row or column.
+        assert_eq!(
+            element_bit(1) | element_bit(2) | element_bit(4),
+            grid.candidates(11)
+        );
+        let text = format(&grid);
+        assert!(text.starts_with("regions\nAAAB\n"), "{}", text);
+        let read = parse(&text).unwrap();
+        assert_eq!(grid.candidates, read.candidates);
+        assert_eq!(2, read.box_of(5));
+    }
+
+    #[test]
+    fn given_formatted_grid_parse_should_read_it_back() {
+        let grid = parse_grid(include_str!("../samples/very-hard.sudoku")).unwrap();
+        let mut grid = CandidateGrid::new(&grid);
+        // Cells with one candidate would be read back as filled.
+        while let Some(i) = (0..grid.size()).find(|&i| grid.candidates(i).count_ones() == 1) {
+            grid.place(i, mask_elements(grid.candidates(i)).next().unwrap());
+        }
+        let index = (0..grid.size())
+            .find(|&i| grid.candidates(i).count_ones() > 2)
+            .unwrap();
+        let value = mask_elements(grid.candidates(index)).next().unwrap();
+        assert!(grid.eliminate(index, value));
+        let text = format(&grid);
+        let read = parse(&text).unwrap();
+        assert_eq!(text, format(&read));
+        assert!(!read.has(index, value));
+    }
+
+    #[test]
+    fn given_64x64_grid_format_should_separate_candidates_and_parse_should_read_them_back() {
+        let mut grid = CandidateGrid::new(&SudokuGrid::new(64).unwrap());
+        grid.place(0, 62);
+        grid.place(1, 5);
+        for value in 1..=60 {
+            grid.eliminate(2, value);
+        }
+        let text = format(&grid);
+        let first_row: Vec<&str> = text.lines().nth(1).unwrap().split_whitespace().collect();
+        assert_eq!(["|", "62", "5", "61,63,64"], first_row[..4]);
+        let read = parse(&text).unwrap();
+        assert_eq!(62, read.grid().cells[0]);
+        assert_eq!(grid.candidates, read.candidates);
+        assert_eq!(text, format(&read));
+    }
+
+    #[test]
+    fn given_hodoku_style_layout_parse_should_read_candidates() {
+        let read = parse(
+            "*-----------*\n\
+             |1  234 |  24  2|\n\
+             |24 24  |  3   12|\n\
+             |---+---|\n\
+             |234 1234 | 12 123|\n\
+             |23 123 |12 4|\n",
+        )
+        .unwrap();
+        assert_eq!(1, read.grid().cells[0]);
+        assert_eq!(element_bit(2) | element_bit(4), read.candidates(4));
+    }
+
+    #[test]
+    fn given_eliminated_candidate_restore_should_put_it_back() {
+        let mut grid = CandidateGrid::new(&parse_grid("1... ..3. .... ...4").unwrap());
+        assert!(grid.eliminate(1, 2));
+        assert!(!grid.has(1, 2));
+        assert!(grid.restore(1, 2));
+        assert!(grid.has(1, 2));
+        // r1c1 is filled, and r1c2 sees the 1 there.
+        assert!(!grid.restore(0, 2));
+        assert!(!grid.restore(1, 1));
+    }
+
+    #[test]
+    fn given_caged_cell_place_should_remove_value_and_misfits_from_the_cage() {
+        // r1c1 and r4c4 share no house, only a cage of sum 7.
+        let mut grid = SudokuGrid::new(4).unwrap();
+        grid.add_cage(7, &[0, 15]).unwrap();
+        let mut grid = CandidateGrid::new(&grid);
+        assert_eq!(element_bit(3) | element_bit(4), grid.candidates(15));
+        grid.place(0, 3);
+        assert_eq!(element_bit(4), grid.candidates(15));
+    }
+
+    #[test]
+    fn given_wrong_count_or_element_parse_should_fail() {
+        assert!(parse("12 34 5").is_err());
+        assert!(parse("1 2 3 4  5 6 7 8  9 1 2 3  4 5 6 7").is_err());
+        assert!(parse("1 2 3 4  3 4 1 2  2 1 4 3  4 3 2 19").is_err());
+    }
+}
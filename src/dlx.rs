@@ -0,0 +1,386 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! An alternative solver treating sudoku as an exact cover problem,
+//! solved with Knuth's Algorithm X using Dancing Links.
+//!
+//! Every possible placement of an element in a cell is a row of the
+//! matrix. Every constraint is a column: each cell holds exactly one
+//! element, and each house holds each element exactly once. A solution
+//! is a set of rows covering every column exactly once.
+
+use crate::{check, element_bit, ElementType, SizeType, SudokuError, SudokuGrid};
+
+/// Sparse 0/1 matrix where every 1 is a node linked to its neighbors
+/// in the same row (left/right) and column (up/down), all lists circular.
+///
+/// Node 0 is the root, nodes `1..=columns` are column headers and the
+/// rest are the 1s of the matrix.
+struct Matrix {
+    left: Vec<SizeType>,
+    right: Vec<SizeType>,
+    up: Vec<SizeType>,
+    down: Vec<SizeType>,
+
+    // Column header of node.
+    column: Vec<SizeType>,
+
+    // Matrix row of node, unused for root and headers.
+    row: Vec<SizeType>,
+
+    // Number of nodes in column, only used for headers.
+    size: Vec<SizeType>,
+}
+
+const ROOT: SizeType = 0;
+
+impl Matrix {
+    fn new(columns: SizeType) -> Matrix {
+        let headers = columns + 1;
+        Matrix {
+            left: (0..headers).map(|i| (i + headers - 1) % headers).collect(),
+            right: (0..headers).map(|i| (i + 1) % headers).collect(),
+            up: (0..headers).collect(),
+            down: (0..headers).collect(),
+            column: (0..headers).collect(),
+            row: vec![0; headers],
+            size: vec![0; headers],
+        }
+    }
+
+    /// Appends a row with 1s in the given columns.
+    fn add_row(&mut self, row: SizeType, columns: &[SizeType]) {
+        let first = self.left.len();
+        for (i, &c) in columns.iter().enumerate() {
+            let header = c + 1;
+            let node = first + i;
+            self.left.push(if i == 0 { first + columns.len() - 1 } else { node - 1 });
+            self.right.push(if i == columns.len() - 1 { first } else { node + 1 });
+            self.up.push(self.up[header]);
+            self.down.push(header);
+            self.column.push(header);
+            self.row.push(row);
+            let above = self.up[header];
+            self.down[above] = node;
+            self.up[header] = node;
+            self.size[header] += 1;
+        }
+    }
+
+    /// Removes column `c` from the header list and all rows with a 1
+    /// in column `c` from the other columns.
+    fn cover(&mut self, c: SizeType) {
+        let (l, r) = (self.left[c], self.right[c]);
+        self.right[l] = r;
+        self.left[r] = l;
+        let mut i = self.down[c];
+        while i != c {
+            let mut j = self.right[i];
+            while j != i {
+                let (u, d) = (self.up[j], self.down[j]);
+                self.down[u] = d;
+                self.up[d] = u;
+                self.size[self.column[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    /// Exactly reverses `cover(c)`.
+    fn uncover(&mut self, c: SizeType) {
+        let mut i = self.up[c];
+        while i != c {
+            let mut j = self.left[i];
+            while j != i {
+                let (u, d) = (self.up[j], self.down[j]);
+                self.down[u] = j;
+                self.up[d] = j;
+                self.size[self.column[j]] += 1;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        let (l, r) = (self.left[c], self.right[c]);
+        self.right[l] = c;
+        self.left[r] = c;
+    }
+
+    /// Returns the uncovered column with fewest 1s, or `None` if all
+    /// columns are covered.
+    fn choose_column(&self) -> Option<SizeType> {
+        let mut best = None;
+        let mut best_size = SizeType::MAX;
+        let mut c = self.right[ROOT];
+        while c != ROOT {
+            if self.size[c] < best_size {
+                best = Some(c);
+                best_size = self.size[c];
+            }
+            c = self.right[c];
+        }
+        best
+    }
+}
+
+/// Iterator over the solutions of a grid, found with Dancing Links.
+pub struct DlxSolver {
+    grid: SudokuGrid,
+
+    matrix: Matrix,
+
+    // Nodes of the rows chosen so far, outermost choice first.
+    chosen: Vec<SizeType>,
+
+    // Set when the first call to `next` has started the search.
+    started: bool,
+
+    // Set when all possibilities have been exhausted.
+    done: bool,
+}
+
+impl DlxSolver {
+    pub fn new(grid: SudokuGrid) -> DlxSolver {
+        let n = grid.elements;
+        let houses = grid.houses();
+        let mut cell_houses = vec![Vec::new(); grid.size];
+        for (h, house) in houses.iter().enumerate() {
+            for &index in house {
+                cell_houses[index].push(h);
+            }
+        }
+
+        // Columns: first one per cell, then one per house and element.
+        let mut matrix = Matrix::new(grid.size + houses.len() * n);
+        let mut columns = Vec::new();
+        for (index, &value) in grid.cells.iter().enumerate() {
+            // Only add rows that agree with the given elements, the rest
+            // could never be part of a solution anyway.
+            let candidates = match value {
+                0 => grid.candidates(index),
+                _ => element_bit(value),
+            };
+            for v in 0..n {
+                if candidates & element_bit(v as ElementType + 1) == 0 {
+                    continue;
+                }
+                columns.clear();
+                columns.push(index);
+                for &h in &cell_houses[index] {
+                    columns.push(grid.size + h * n + v);
+                }
+                matrix.add_row(index * n + v, &columns);
+            }
+        }
+
+        DlxSolver {
+            grid,
+            matrix,
+            chosen: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Chooses the row of `node`, covering the rest of its columns.
+    fn select(&mut self, node: SizeType) {
+        self.chosen.push(node);
+        let mut j = self.matrix.right[node];
+        while j != node {
+            self.matrix.cover(self.matrix.column[j]);
+            j = self.matrix.right[j];
+        }
+    }
+
+    /// Exactly reverses `select(node)`, except for the push to `chosen`.
+    fn unselect(&mut self, node: SizeType) {
+        let mut j = self.matrix.left[node];
+        while j != node {
+            self.matrix.uncover(self.matrix.column[j]);
+            j = self.matrix.left[j];
+        }
+    }
+
+    /// Replaces the innermost choice by the next row in the same column,
+    /// back-tracking to outer choices whenever a column runs out of rows.
+    /// Returns `false` when there is nothing left to try.
+    fn backtrack(&mut self) -> bool {
+        while let Some(node) = self.chosen.pop() {
+            self.unselect(node);
+            let c = self.matrix.column[node];
+            let next = self.matrix.down[node];
+            if next != c {
+                self.select(next);
+                return true;
+            }
+            self.matrix.uncover(c);
+        }
+        false
+    }
+
+    /// The grid described by the chosen rows.
+    fn solution(&self) -> SudokuGrid {
+        let n = self.grid.elements;
+        let mut solution = self.grid.clone();
+        for &node in &self.chosen {
+            let row = self.matrix.row[node];
+            solution.cells[row / n] = (row % n + 1) as ElementType;
+        }
+        solution
+    }
+}
+
+impl DlxSolver {
+    /// Chooses rows until they form the next solution. Returns `false`
+    /// if there are no more solutions.
+    fn find_next(&mut self) -> bool {
+        if self.done {
+            return false;
+        }
+        let alive = if self.started {
+            // The previous call found a solution, continue with
+            // the other possibilities.
+            self.backtrack()
+        } else {
+            self.started = true;
+            // 0x0 grids have no solutions.
+            self.grid.size > 0
+        };
+        if !alive {
+            self.done = true;
+            return false;
+        }
+        loop {
+            match self.matrix.choose_column() {
+                None => {
+                    // All columns covered, so we have a solution.
+                    return true;
+                }
+                Some(c) if self.matrix.size[c] == 0 => {
+                    // Constraint that can't be satisfied; dead end.
+                    if !self.backtrack() {
+                        self.done = true;
+                        return false;
+                    }
+                }
+                Some(c) => {
+                    self.matrix.cover(c);
+                    let node = self.matrix.down[c];
+                    self.select(node);
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for DlxSolver {
+    type Item = SudokuGrid;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.find_next() {
+            Some(self.solution())
+        } else {
+            None
+        }
+    }
+
+    /// Counts the remaining solutions without building a grid for each.
+    fn count(mut self) -> usize {
+        let mut count = 0;
+        while self.find_next() {
+            count += 1;
+        }
+        count
+    }
+}
+
+/// Returns an iterator which will provide the solutions, found with
+/// Dancing Links instead of the back-tracking `SudokuSolver`. The
+/// solutions are the same, but may come in a different order. Grids
+/// with cages aren't supported.
+pub fn solutions(grid: &SudokuGrid) -> Result<DlxSolver, SudokuError> {
+    if !grid.cages().is_empty() {
+        return Err(SudokuError::UnsupportedCages);
+    }
+    check(grid)?;
+    Ok(DlxSolver::new(grid.clone()))
+}
+
+/// Like `crate::count_solutions`, but using Dancing Links.
+pub fn count_solutions(grid: &SudokuGrid, limit: Option<usize>) -> Result<usize, SudokuError> {
+    let mut solver = solutions(grid)?;
+    let mut count = 0;
+    while limit.is_none_or(|limit| count < limit) && solver.find_next() {
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    /// Solutions of `grid` from both solvers, sorted.
+    fn both_solvers(grid: &SudokuGrid) -> (Vec<Vec<ElementType>>, Vec<Vec<ElementType>>) {
+        let mut backtracked: Vec<_> = crate::solutions(grid).unwrap().map(|s| s.cells).collect();
+        let mut linked: Vec<_> = solutions(grid).unwrap().map(|s| s.cells).collect();
+        backtracked.sort();
+        linked.sort();
+        (backtracked, linked)
+    }
+
+    #[test]
+    fn given_0x0_grid_solve_shall_return_no_solutions() {
+        let grid = SudokuGrid::load(&[]).unwrap();
+        assert_eq!(0, solutions(&grid).unwrap().count());
+    }
+
+    #[test]
+    fn given_1x1_grid_solve_shall_return_1x1_grid_with_1_in_cell() {
+        let grid = SudokuGrid::load(&[0]).unwrap();
+        let all: Vec<SudokuGrid> = solutions(&grid).unwrap().collect();
+        assert_eq!(1, all.len());
+        assert_eq!(vec![1], all[0].cells);
+    }
+
+    #[test]
+    fn given_contradictory_4x4_grid_should_get_no_solution_iterator() {
+        let grid = parse("1234 4321 .2.. ....").unwrap();
+        assert!(solutions(&grid).is_err());
+    }
+
+    #[test]
+    fn given_empty_4x4_grid_should_agree_with_backtracker() {
+        let (backtracked, linked) = both_solvers(&SudokuGrid::new(4).unwrap());
+        assert_eq!(288, linked.len());
+        assert_eq!(backtracked, linked);
+    }
+
+    #[test]
+    fn given_empty_4x4_grid_count_solutions_should_stop_at_limit() {
+        let grid = SudokuGrid::new(4).unwrap();
+        assert_eq!(288, count_solutions(&grid, None).unwrap());
+        assert_eq!(10, count_solutions(&grid, Some(10)).unwrap());
+    }
+
+    #[test]
+    fn given_samples_should_agree_with_backtracker() {
+        for input in [
+            include_str!("../samples/easy.sudoku"),
+            include_str!("../samples/very-hard.sudoku"),
+            include_str!("../samples/with-many-solutions.sudoku"),
+            include_str!("../samples/hard-16x16.sudoku"),
+        ] {
+            let grid = parse(input).unwrap();
+            let (backtracked, linked) = both_solvers(&grid);
+            assert!(!linked.is_empty());
+            assert_eq!(backtracked, linked);
+        }
+    }
+
+    #[test]
+    fn given_grid_with_cages_solutions_should_fail() {
+        let grid = parse(include_str!("../samples/killer.sudoku")).unwrap();
+        assert_eq!(Some(SudokuError::UnsupportedCages), solutions(&grid).err());
+    }
+}
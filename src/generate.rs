@@ -0,0 +1,227 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Creating new grids and puzzles from a seed. The same seed gives the
+//! same grid on every machine.
+
+use crate::random::Random;
+use crate::symmetry::Symmetry;
+use crate::{
+    count_solutions, solutions_with_options, ElementType, SizeType, SolverOptions, SudokuError,
+    SudokuGrid,
+};
+
+/// Options for `generate_puzzle`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PuzzleOptions {
+    /// Number of elements, no larger than 64 and with boxes to fit it
+    /// as in `SudokuGrid::new`, normally 9.
+    pub elements: ElementType,
+
+    /// Stop removing clues when this few are left. Puzzles where no more
+    /// clues can be removed without losing uniqueness end up with more.
+    /// Zero removes as many clues as possible.
+    pub target_clues: SizeType,
+
+    /// Symmetry of the pattern of clues.
+    pub symmetry: Symmetry,
+
+    pub seed: u64,
+}
+
+impl Default for PuzzleOptions {
+    fn default() -> PuzzleOptions {
+        PuzzleOptions {
+            elements: 9,
+            target_clues: 0,
+            symmetry: Symmetry::None,
+            seed: 0,
+        }
+    }
+}
+
+/// Returns a puzzle with exactly one solution.
+///
+/// Starts from a completed grid from `generate_grid` and removes clues in
+/// random order, putting back any clue whose removal would allow a second
+/// solution. Clues are removed together with the clues the symmetry pairs
+/// them with, so the pattern stays symmetric.
+pub fn generate_puzzle(options: &PuzzleOptions) -> Result<SudokuGrid, SudokuError> {
+    let mut random = Random::new(options.seed);
+    let mut puzzle = generate_grid(options.elements, random.next_u64())?;
+    let mut order: Vec<SizeType> = (0..puzzle.size).collect();
+    random.shuffle(&mut order);
+    let mut clues = puzzle.size;
+    for index in order {
+        if clues <= options.target_clues {
+            break;
+        }
+        if puzzle.cells[index] == 0 {
+            // Already removed as part of an earlier orbit.
+            continue;
+        }
+        let orbit = options.symmetry.orbit(puzzle.elements, index);
+        if clues - orbit.len() < options.target_clues {
+            continue;
+        }
+        let values: Vec<ElementType> = orbit.iter().map(|&i| puzzle.cells[i]).collect();
+        for &i in &orbit {
+            puzzle.set_given(i, 0);
+        }
+        if count_solutions(&puzzle, Some(2))? == 1 {
+            clues -= orbit.len();
+        } else {
+            for (&i, &value) in orbit.iter().zip(&values) {
+                puzzle.set_given(i, value);
+            }
+        }
+    }
+    Ok(puzzle)
+}
+
+/// Returns a random completed grid with `elements` elements, no more
+/// than 64, and boxes of the usual shape as in `SudokuGrid::new`.
+///
+/// The grid is found by solving an empty grid while trying values in
+/// random order, then shuffled further by relabeling elements and
+/// reordering rows and columns in ways that keep it valid.
+pub fn generate_grid(elements: ElementType, seed: u64) -> Result<SudokuGrid, SudokuError> {
+    if elements == 0 {
+        return Err(SudokuError::InvalidElements(0));
+    }
+    let empty = SudokuGrid::new(elements)?;
+    let mut random = Random::new(seed);
+    let options = SolverOptions {
+        shuffle_seed: Some(random.next_u64()),
+        ..Default::default()
+    };
+    let solved = solutions_with_options(&empty, options)?
+        .next()
+        .expect("empty grids always have a solution");
+    Ok(shuffle_grid(&solved, &mut random))
+}
+
+/// Returns `grid` with elements relabeled, bands and stacks reordered,
+/// rows and columns reordered within them, and maybe transposed; all
+/// at random. A valid grid stays valid.
+fn shuffle_grid(grid: &SudokuGrid, random: &mut Random) -> SudokuGrid {
+    let n = grid.elements;
+    let mut labels: Vec<ElementType> = (1..=n as ElementType).collect();
+    random.shuffle(&mut labels);
+    let row_order = shuffled_lines(n / grid.box_height, grid.box_height, random);
+    let col_order = shuffled_lines(n / grid.box_width, grid.box_width, random);
+    // Transposing turns boxes on their side, so only keeps square ones.
+    let transpose = grid.box_width == grid.box_height && random.below(2) == 1;
+
+    let mut shuffled = grid.clone();
+    for index in 0..grid.size {
+        let (row, col) = (row_order[index / n], col_order[index % n]);
+        let from_index = match transpose {
+            false => row * n + col,
+            true => col * n + row,
+        };
+        // The solver filled in the grid, but to the caller it's all givens.
+        let value = match grid.cells[from_index] {
+            0 => 0,
+            value => labels[value as SizeType - 1],
+        };
+        shuffled.set_given(index, value);
+    }
+    shuffled
+}
+
+/// Returns the rows (or columns) of a grid in an order where the
+/// `count` bands (or stacks) of `width` lines are shuffled, and the
+/// lines within each of them too.
+fn shuffled_lines(count: SizeType, width: SizeType, random: &mut Random) -> Vec<SizeType> {
+    let mut bands: Vec<SizeType> = (0..count).collect();
+    random.shuffle(&mut bands);
+    let mut lines = Vec::with_capacity(count * width);
+    for band in bands {
+        let mut within: Vec<SizeType> = (0..width).collect();
+        random.shuffle(&mut within);
+        lines.extend(within.iter().map(|line| band * width + line));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check;
+    use crate::symmetry::symmetries_of;
+
+    #[test]
+    fn given_supported_sizes_generate_grid_should_return_full_valid_grid() {
+        for elements in [1, 4, 6, 8, 9, 10, 12, 16] {
+            let grid = generate_grid(elements, 1).unwrap();
+            assert!(grid.cells.iter().all(|&x| x != 0), "{} elements", elements);
+            assert!(check(&grid).is_ok(), "{} elements", elements);
+        }
+    }
+
+    #[test]
+    fn given_6_elements_generate_puzzle_should_return_unique_puzzle_with_3x2_boxes() {
+        let options = PuzzleOptions { elements: 6, seed: 3, ..Default::default() };
+        let puzzle = generate_puzzle(&options).unwrap();
+        assert_eq!((3, 2), (puzzle.box_width, puzzle.box_height));
+        assert_eq!(1, count_solutions(&puzzle, None).unwrap());
+    }
+
+    #[test]
+    fn given_same_seed_generate_grid_should_return_same_grid() {
+        let a = generate_grid(9, 1234).unwrap();
+        let b = generate_grid(9, 1234).unwrap();
+        assert_eq!(a.cells, b.cells);
+    }
+
+    #[test]
+    fn given_different_seeds_generate_grid_should_return_different_grids() {
+        let a = generate_grid(9, 1).unwrap();
+        let b = generate_grid(9, 2).unwrap();
+        assert_ne!(a.cells, b.cells);
+    }
+
+    #[test]
+    fn given_default_options_generate_puzzle_should_return_unique_puzzle() {
+        let options = PuzzleOptions { seed: 5, ..Default::default() };
+        let puzzle = generate_puzzle(&options).unwrap();
+        assert_eq!(1, count_solutions(&puzzle, None).unwrap());
+        // Minimal puzzles have far fewer clues than this.
+        assert!(puzzle.cells.iter().filter(|&&x| x != 0).count() < 40);
+    }
+
+    #[test]
+    fn given_target_clues_generate_puzzle_should_stop_there() {
+        let options = PuzzleOptions { target_clues: 50, seed: 5, ..Default::default() };
+        let puzzle = generate_puzzle(&options).unwrap();
+        assert_eq!(50, puzzle.cells.iter().filter(|&&x| x != 0).count());
+        assert_eq!(50, (0..puzzle.size).filter(|&i| puzzle.is_given(i)).count());
+        assert_eq!(1, count_solutions(&puzzle, None).unwrap());
+    }
+
+    #[test]
+    fn given_same_seed_generate_puzzle_should_return_same_puzzle() {
+        let options = PuzzleOptions { elements: 4, seed: 77, ..Default::default() };
+        let a = generate_puzzle(&options).unwrap();
+        let b = generate_puzzle(&options).unwrap();
+        assert_eq!(a.cells, b.cells);
+    }
+
+    #[test]
+    fn given_each_symmetry_generate_puzzle_should_return_puzzle_with_it() {
+        for symmetry in Symmetry::ALL {
+            let options = PuzzleOptions { symmetry, seed: 11, ..Default::default() };
+            let puzzle = generate_puzzle(&options).unwrap();
+            assert!(symmetries_of(&puzzle).contains(&symmetry), "{}", symmetry);
+            assert_eq!(1, count_solutions(&puzzle, None).unwrap(), "{}", symmetry);
+        }
+    }
+
+    #[test]
+    fn given_unsupported_size_generate_grid_should_fail() {
+        assert!(generate_grid(0, 1).is_err());
+        assert!(generate_grid(7, 1).is_err());
+        assert!(generate_grid(81, 1).is_err());
+    }
+}
@@ -1,287 +1,1378 @@
 // © Copyright 2021 Sudoku Rustic’s Authors
 // Subject to the MIT License. See file LICENSE for details.
 
+pub mod candidates;
+pub mod dlx;
+pub mod generate;
+pub mod logic;
+pub mod random;
+pub mod rating;
+pub mod symmetry;
+pub mod variants;
+
+use std::error::Error;
+use std::fmt;
+
+use random::Random;
+use variants::ExtraHouses;
+
 type ElementType = u8; // Capable of containg all elements plus empty, normally 0..=9.
 type SizeType = usize; // Capable of indexing all cells in a grid plus one, normally 82.
+type MaskType = u64; // Set of elements, bit `v - 1` for element `v`; enough for 64 elements.
+type BoxShape = (SizeType, SizeType); // Width and height of a box, normally (3, 3).
+
+/// Most elements a grid may have, as many as fit in a `MaskType`.
+const MAX_ELEMENTS: SizeType = 64;
+
+/// Grids with more elements than this are written as numbers, as there
+/// are only so many single characters: 1 to 9 and A to Z.
+const MAX_CHAR_ELEMENTS: SizeType = 35;
+
+/// The characters `format` names regions by in the region map, one for
+/// each of up to `MAX_ELEMENTS` regions.
+const REGION_NAMES: &[u8; MAX_ELEMENTS] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789@#";
 
 /// A sudoku grid.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SudokuGrid {
     /// The cells of the grid starting with top-left cell
     /// followed by rest of first row, then continues row
     /// by row.
     cells: Vec<ElementType>, // has len() = size, empty cells have value zero.
 
+    /// Whether each cell was given in the puzzle, as opposed to filled
+    /// in by a solver. Only non-empty cells are given.
+    given: Vec<bool>,
+
     size: SizeType,     // =elements²; normally 81.
 
     elements: SizeType, // =√size; values ranges from 1 to this, normally 9.
                         // Also number of cells in row/column/box.
 
-    boxsize: SizeType,  // =√elements; normally 3.
+    box_width: SizeType,  // columns in a box; normally 3.
+
+    box_height: SizeType, // rows in a box; box_width * box_height = elements.
+                          // Grids with `regions` have boxes one row high.
+
+    /// The region of every cell, numbered from 0, for grids where the
+    /// regions aren't boxes, as in jigsaw sudoku. `None` if they are.
+    regions: Option<Vec<SizeType>>,
+
+    /// Houses besides rows, columns and boxes, in the order added.
+    extra: Vec<ExtraHouses>,
+
+    /// Cages of a Killer sudoku, in the order added.
+    cages: Vec<Cage>,
+}
+
+/// A cage of a Killer sudoku: cells whose elements add up to `sum`, with
+/// no element more than once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cage {
+    pub sum: usize,
+
+    /// Indexes of the cells, in the order given.
+    pub cells: Vec<usize>,
 }
 
 impl SudokuGrid {
-    /// Creates a new grid. `elements` must be a perfect
-    /// square, normally 9, maximum 16 (currently).
-    pub fn new(elements: ElementType) -> SudokuGrid {
-        assert!(elements <= 16, "maximum number of elements is 16.");
-        let boxsize = (elements as f64).sqrt() as SizeType;
-        let elements = SizeType::from(elements);
-        assert_eq!(
-            elements,
-            boxsize.pow(2),
-            "elements must be a perfect square."
-        );
+    /// Creates a new grid with `elements` elements, normally 9,
+    /// maximum 64, and boxes of the usual shape for that:
+    /// square if `elements` is a perfect square, else as square as
+    /// possible and wider than high, like 3x2 for 6 elements.
+    pub fn new(elements: ElementType) -> Result<SudokuGrid, SudokuError> {
+        let (box_width, box_height) = default_boxes(SizeType::from(elements))?;
+        SudokuGrid::with_boxes(box_width as ElementType, box_height as ElementType)
+    }
+
+    /// Creates a new grid with boxes `box_width` columns wide and
+    /// `box_height` rows high, so with `box_width * box_height`
+    /// elements.
+    pub fn with_boxes(box_width: ElementType, box_height: ElementType) -> Result<SudokuGrid, SudokuError> {
+        let (box_width, box_height) = (SizeType::from(box_width), SizeType::from(box_height));
+        let elements = box_width * box_height;
+        if elements > MAX_ELEMENTS {
+            return Err(SudokuError::TooLarge { elements, maximum: MAX_ELEMENTS });
+        }
         let size = elements.pow(2);
         let cells = vec![0; size];
-        SudokuGrid {
+        Ok(SudokuGrid {
             cells,
+            given: vec![false; size],
             size,
             elements,
-            boxsize,
-        }
+            box_width,
+            box_height,
+            regions: None,
+            extra: Vec::new(),
+            cages: Vec::new(),
+        })
     }
 
-    /// Returns a grid preloaded with the values in `cell_values`.
-    /// Length of argument must be a perfect square of a perfect square.
+    /// Returns a grid preloaded with the values in `cell_values`, with
+    /// boxes of the usual shape as in `new`.
+    /// Length of argument must be a perfect square.
     /// `cell_values` represents the cells of the grid starting with
     /// top-left cell followed by rest of first row, then continues row
     /// by row.
     ///
     /// A value of 0 means empty.
-    /// Any other number is an element in that cell.
-    /// Maximum length is 256*256 = 65536
-    pub fn load(cell_values: &Vec<ElementType>) -> Result<SudokuGrid, String> {
-        assert!(cell_values.len() <= 65536, "Won't attempt loading grids larger than 256x256.");
-        let elements_count = (cell_values.len() as f64).sqrt() as u32;
-        let boxsize = (elements_count as f64).sqrt() as SizeType;
-        if boxsize.pow(2).pow(2) != cell_values.len() {
-            return Err(format!("Invalid input, length must be a perfect square of a perfect square. Normally 81. Was: {}", cell_values.len()));
-        }
-        let mut grid = SudokuGrid::new(elements_count as ElementType);
+    /// Any other number is an element in that cell, and is given.
+    /// Maximum length is 64*64 = 4096.
+    pub fn load(cell_values: &[ElementType]) -> Result<SudokuGrid, SudokuError> {
+        let (box_width, box_height) = default_boxes(elements_for_length(cell_values.len())?)?;
+        SudokuGrid::load_with_boxes(cell_values, box_width, box_height)
+    }
+
+    /// Like `load`, but with boxes `box_width` columns wide and
+    /// `box_height` rows high.
+    pub fn load_with_boxes(
+        cell_values: &[ElementType],
+        box_width: SizeType,
+        box_height: SizeType,
+    ) -> Result<SudokuGrid, SudokuError> {
+        let elements_count = elements_for_length(cell_values.len())?;
+        if elements_count > MAX_ELEMENTS {
+            return Err(SudokuError::TooLarge { elements: elements_count, maximum: MAX_ELEMENTS });
+        }
+        if box_width.checked_mul(box_height) != Some(elements_count) {
+            return Err(SudokuError::InvalidBoxes { box_width, box_height, elements: elements_count });
+        }
+        let mut grid = SudokuGrid::with_boxes(box_width as ElementType, box_height as ElementType)?;
         for (i, elem) in cell_values.iter().enumerate() {
             if *elem == 0 {
                 continue;
             }
+            if SizeType::from(*elem) > elements_count {
+                return Err(SudokuError::ElementOutOfRange { index: i, value: *elem });
+            }
             grid.cells[i] = *elem;
+            grid.given[i] = true;
         }
         Ok(grid)
     }
 
+    /// Like `load`, but with irregular regions instead of boxes, as in
+    /// jigsaw sudoku. `regions` has the region of every cell, numbered
+    /// from 0, in the same order as `cell_values`. Every region must
+    /// have as many cells as there are elements.
+    pub fn load_with_regions(cell_values: &[ElementType], regions: &[SizeType]) -> Result<SudokuGrid, SudokuError> {
+        if regions.len() != cell_values.len() {
+            return Err(SudokuError::InvalidLength(regions.len()));
+        }
+        let elements = elements_for_length(cell_values.len())?;
+        let mut grid = SudokuGrid::load_with_boxes(cell_values, elements, 1)?;
+        // If all regions up to `elements` are full no cells are left for
+        // any others.
+        for region in 0..elements {
+            let cells = regions.iter().filter(|&&r| r == region).count();
+            if cells != elements {
+                return Err(SudokuError::InvalidRegion { region, cells, elements });
+            }
+        }
+        grid.regions = Some(regions.to_vec());
+        Ok(grid)
+    }
+
+    /// The element in the cell at `index`, 0 if empty. Cells are
+    /// numbered from the top-left cell, row by row.
+    pub fn value(&self, index: usize) -> ElementType {
+        self.cells[index]
+    }
+
+    /// True if the cell at `index` was given in the puzzle.
+    pub fn is_given(&self, index: usize) -> bool {
+        self.given[index]
+    }
+
+    /// True if the cell at `index` was filled in by a solver rather
+    /// than given.
+    pub fn is_filled_by_solver(&self, index: usize) -> bool {
+        self.cells[index] != 0 && !self.given[index]
+    }
+
+    /// Adds the `extra` houses to the grid, where every element must
+    /// also appear once, as for a variant like Sudoku-X. Fails if the
+    /// grid has irregular regions and these follow the boxes.
+    pub fn add_extra_houses(&mut self, extra: ExtraHouses) -> Result<(), SudokuError> {
+        if self.regions.is_some() && !extra.fit_regions() {
+            return Err(SudokuError::UnsupportedExtraHouses(extra));
+        }
+        if !self.extra.contains(&extra) {
+            self.extra.push(extra);
+        }
+        Ok(())
+    }
+
+    /// The extra houses of the grid, in the order added.
+    pub fn extra_houses(&self) -> &[ExtraHouses] {
+        &self.extra
+    }
+
+    /// Adds a cage of the cells at `cells` with elements adding up to
+    /// `sum`, as in Killer sudoku. Fails if some cell is outside the grid
+    /// or already in a cage, or no set of different elements fits.
+    pub fn add_cage(&mut self, sum: usize, cells: &[usize]) -> Result<(), SudokuError> {
+        let invalid = || SudokuError::InvalidCage { sum, cells: cells.to_vec() };
+        for (k, &index) in cells.iter().enumerate() {
+            let caged = self.cages.iter().any(|cage| cage.cells.contains(&index));
+            if index >= self.size || caged || cells[..k].contains(&index) {
+                return Err(invalid());
+            }
+        }
+        if cells.is_empty() || sum_combinations(full_mask(self.elements), cells.len(), sum).is_none() {
+            return Err(invalid());
+        }
+        self.cages.push(Cage { sum, cells: cells.to_vec() });
+        Ok(())
+    }
+
+    /// The cages of the grid, in the order added.
+    pub fn cages(&self) -> &[Cage] {
+        &self.cages
+    }
+
+    /// Elements that could go in the cell at `index` of `cage` as far as
+    /// the other cells of the cage tell: none of theirs, and adding up to
+    /// the sum together with different elements in the empty ones.
+    fn cage_fits(&self, cage: &Cage, index: SizeType) -> MaskType {
+        let others: Vec<SizeType> = cage.cells.iter().copied().filter(|&i| i != index).collect();
+        let placed = others.iter().fold(0, |mask, &i| mask | element_bit(self.cells[i]));
+        let placed_sum: SizeType = others.iter().map(|&i| SizeType::from(self.cells[i])).sum();
+        let empty = others.iter().filter(|&&i| self.cells[i] == 0).count();
+        match cage.sum.checked_sub(placed_sum) {
+            Some(left) => sum_combinations(full_mask(self.elements) & !placed, empty + 1, left).unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    /// Sets the cell at `index` as a given, or empties it if `value`
+    /// is 0.
+    fn set_given(&mut self, index: SizeType, value: ElementType) {
+        self.cells[index] = value;
+        self.given[index] = value != 0;
+    }
+
     /// Get possible values for a cell based on its neighbors
     /// but not itself, in arbitrary order.
+    #[cfg(test)]
     fn possibilities(&self, index: SizeType) -> Vec<ElementType> {
-        // `pmap` will contain `true` at `map[i]` if `i` is possible.
-        let mut pmap = vec![true; self.elements + 1];
-        // `pmap[0]` will not be used when constructing result, but set it to false just in case.
-        pmap[0] = false;
+        mask_elements(self.candidates(index)).collect()
+    }
+
+    /// Get possible values for a cell based on its neighbors
+    /// but not itself, as a mask with bit `v - 1` set if element
+    /// `v` is possible.
+    fn candidates(&self, index: SizeType) -> MaskType {
         let rowstart_index = (index / self.elements) * self.elements;
         let colstart_index = index % self.elements;
 
-        // Which box column, has value in range `0..self.boxsize`.
-        let boxcol = (index - rowstart_index) / self.boxsize;
-        // Which box row, has value in range `0..self.boxsize`.
-        let boxrow = (index - colstart_index) / (self.elements * self.boxsize);
+        // Which box column, has value in range `0..self.elements / self.box_width`.
+        let boxcol = (index - rowstart_index) / self.box_width;
+        // Which box row, has value in range `0..self.elements / self.box_height`.
+        let boxrow = (index - colstart_index) / (self.elements * self.box_height);
         // Top left corner of box:
-        let boxbase_index = boxrow * self.boxsize * self.elements // row
-                            + boxcol * self.boxsize; // column
+        let boxbase_index = boxrow * self.box_height * self.elements // row
+                            + boxcol * self.box_width; // column
 
-        // Now set `pmap` to false at index corresponding to element if that element is part of the
-        // row, column or box already.
+        // Now set a bit in `used` for every element that is part of the row,
+        // column or box already.
         // We look at each cell in the row/column/box in turn to find such elements.
         // We loop over `0..self.element` for this because that is how many cells there are in a
         // row/column/box, not because we look at each element in turn.
-        // If the row/column/box contains a zero at index `i`, or looking at the `index` cell
-        // itself, it will set `pmap[0]` to false, but since that has no effect when the result
-        // is constructed this does not matter. Probably faster to just set `pmap[0]` than checking
-        // if a write to `pmap` should be skipped.
+        // Empty cells, and the `index` cell itself, contribute no bit.
+        let mut used: MaskType = 0;
         for i in 0..self.elements {
             // row
-            pmap[self.read_value_at_index(i + rowstart_index, index)] = false;
+            used |= element_bit(self.read_value_at_index(i + rowstart_index, index));
             // column
-            pmap[self.read_value_at_index(
-                (i * self.elements) + colstart_index, index)] = false;
+            used |= element_bit(self.read_value_at_index(
+                (i * self.elements) + colstart_index, index));
+            if self.regions.is_some() {
+                continue;
+            }
             // box
-            pmap[self.read_value_at_index(
+            used |= element_bit(self.read_value_at_index(
                 // This calculation is dense?
-                // Could make two for loops of 0..self.boxsize instead
+                // Could make two for loops of 0..self.box_height and 0..self.box_width instead
                 boxbase_index
-                 + (i % self.boxsize) // loop columns
-                 + (i / self.boxsize) * self.elements // loop rows
-                , index)] = false;
+                 + (i % self.box_width) // loop columns
+                 + (i / self.box_width) * self.elements // loop rows
+                , index));
         }
-
-        // Construct result.
-        let mut result = Vec::with_capacity(self.elements);
-        for (i, is_possible) in pmap.iter().enumerate() {
-            if i > 0 && *is_possible {
-                result.push(i as ElementType);
+        // Irregular regions have no formula, so look at every cell.
+        if let Some(regions) = &self.regions {
+            for (i, &region) in regions.iter().enumerate() {
+                if region == regions[index] {
+                    used |= element_bit(self.read_value_at_index(i, index));
+                }
+            }
+        }
+        for extra in &self.extra {
+            for (_, cells) in extra.houses(self) {
+                if cells.contains(&index) {
+                    for i in cells {
+                        used |= element_bit(self.read_value_at_index(i, index));
+                    }
+                }
             }
         }
+        if let Some(cage) = self.cages.iter().find(|cage| cage.cells.contains(&index)) {
+            used |= !self.cage_fits(cage, index);
+        }
 
-        result
+        full_mask(self.elements) & !used
     }
-    
+
+    /// Returns the cell indexes of every house: first all rows, then all
+    /// columns, then all boxes or regions, then any extra houses.
+    fn houses(&self) -> Vec<Vec<SizeType>> {
+        let n = self.elements;
+        let mut houses = Vec::with_capacity(3 * n);
+        for row in 0..n {
+            houses.push((0..n).map(|col| row * n + col).collect());
+        }
+        for col in 0..n {
+            houses.push((0..n).map(|row| row * n + col).collect());
+        }
+        for b in 0..n {
+            houses.push((0..self.size).filter(|&i| self.region(i) == b).collect());
+        }
+        for extra in &self.extra {
+            houses.extend(extra.houses(self).into_iter().map(|(_, cells)| cells));
+        }
+        houses
+    }
+
+    /// Which region the cell at `index` is in: its box, numbered row by
+    /// row, unless the grid has irregular regions.
+    pub fn region(&self, index: usize) -> usize {
+        if let Some(regions) = &self.regions {
+            return regions[index];
+        }
+        let (row, col) = (index / self.elements, index % self.elements);
+        (row / self.box_height) * (self.elements / self.box_width) + col / self.box_width
+    }
+
+    /// Returns what each of the houses from `houses` is, in the same order.
+    fn house_labels(&self) -> Vec<House> {
+        let n = self.elements;
+        let rows = (0..n).map(House::Row);
+        let columns = (0..n).map(House::Column);
+        let boxes = (0..n).map(House::Box);
+        let extra = self.extra.iter().flat_map(|extra| extra.houses(self).into_iter().map(|(house, _)| house));
+        rows.chain(columns).chain(boxes).chain(extra).collect()
+    }
+
     /// Helper for `possibilities`. Return value in cell at `index`,
     /// except if it is `except_index` in which case it returns `0`.
-    fn read_value_at_index(&self, index: SizeType, except_index: SizeType) -> usize {
+    fn read_value_at_index(&self, index: SizeType, except_index: SizeType) -> ElementType {
         if index == except_index { return 0; }
-        self.cells[index] as usize
+        self.cells[index]
+    }
+}
+
+/// A row, column or box, numbered from zero; top to bottom, left to
+/// right, and for boxes row by row. Irregular regions count as boxes.
+/// The others are the houses of `ExtraHouses`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum House {
+    Row(SizeType),
+    Column(SizeType),
+    Box(SizeType),
+    Diagonal(SizeType),
+    Window(SizeType),
+    Group(SizeType),
+}
+
+impl House {
+    /// True for rows and columns.
+    pub fn is_line(self) -> bool {
+        matches!(self, House::Row(_) | House::Column(_))
+    }
+}
+
+/// Writes the house numbered from one, like "row 3" or "box 2".
+impl fmt::Display for House {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            House::Row(i) => write!(f, "row {}", i + 1),
+            House::Column(i) => write!(f, "column {}", i + 1),
+            House::Box(i) => write!(f, "box {}", i + 1),
+            House::Diagonal(i) => write!(f, "diagonal {}", i + 1),
+            House::Window(i) => write!(f, "window {}", i + 1),
+            House::Group(i) => write!(f, "disjoint group {}", i + 1),
+        }
+    }
+}
+
+/// The number of elements of a grid with `length` cells.
+fn elements_for_length(length: SizeType) -> Result<SizeType, SudokuError> {
+    let elements = (length as f64).sqrt() as SizeType;
+    match elements.pow(2) == length {
+        true => Ok(elements),
+        false => Err(SudokuError::InvalidLength(length)),
+    }
+}
+
+/// The usual width and height of the boxes of a grid with `elements`
+/// elements: the height is the largest divisor not above the square
+/// root. Fails if that leaves boxes a single row high, as for 7.
+fn default_boxes(elements: SizeType) -> Result<BoxShape, SudokuError> {
+    if elements <= 1 {
+        return Ok((elements, elements));
+    }
+    let height = (1..=elements)
+        .take_while(|d| d * d <= elements)
+        .filter(|&d| elements.is_multiple_of(d))
+        .last()
+        .unwrap_or(1);
+    match height {
+        1 => Err(SudokuError::InvalidElements(elements)),
+        _ => Ok((elements / height, height)),
     }
 }
 
+/// What can go wrong when reading or solving a grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SudokuError {
+    /// The number of cells isn't a perfect square.
+    InvalidLength(usize),
+
+    /// No boxes of the usual shape fit that many elements, as it is
+    /// prime, or it is zero where a grid with cells is needed.
+    InvalidElements(usize),
+
+    /// Boxes of that shape don't hold exactly the number of elements.
+    InvalidBoxes { box_width: usize, box_height: usize, elements: usize },
+
+    /// A line declaring the shape of the grid that can't be read.
+    InvalidHeader(String),
+
+    /// A region of a jigsaw grid with other than one cell for every
+    /// element.
+    InvalidRegion { region: usize, cells: usize, elements: usize },
+
+    /// More elements than supported.
+    TooLarge { elements: usize, maximum: usize },
+
+    /// An element larger than the number of elements, in the cell at
+    /// `index`.
+    ElementOutOfRange { index: usize, value: ElementType },
+
+    /// Indexes of the cells holding an element that is also in a house
+    /// they share.
+    Contradiction { cells: Vec<usize> },
+
+    /// The grid has no solution, so some entry is wrong.
+    NoSolution,
+
+    /// A name that isn't one of the symmetries.
+    UnknownSymmetry(String),
+
+    /// A cage with cells outside the grid or in another cage, or that no
+    /// set of different elements adds up to the sum of.
+    InvalidCage { sum: usize, cells: Vec<usize> },
+
+    /// Dancing Links only knows houses, so can't solve grids with cages.
+    UnsupportedCages,
+
+    /// A name that isn't one of the kinds of extra houses.
+    UnknownExtraHouses(String),
+
+    /// Extra houses that follow the boxes, on a grid with irregular
+    /// regions instead.
+    UnsupportedExtraHouses(ExtraHouses),
+}
+
+impl fmt::Display for SudokuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SudokuError::InvalidLength(length) => write!(
+                f,
+                "Invalid input, length must be a perfect square. Normally 81. Was: {}",
+                length
+            ),
+            SudokuError::InvalidElements(elements) => {
+                write!(f, "Invalid number of elements {}, no boxes fit it.", elements)
+            }
+            SudokuError::InvalidBoxes { box_width, box_height, elements } => write!(
+                f,
+                "Boxes of {}x{} don't fit a grid with {} elements.",
+                box_width, box_height, elements
+            ),
+            SudokuError::InvalidRegion { region, cells, elements } => write!(
+                f,
+                "Region {} has {} cells, but needs one for each of the {} elements.",
+                region, cells, elements
+            ),
+            SudokuError::InvalidHeader(line) => write!(f, "Can't read line \"{}\"", line),
+            SudokuError::TooLarge { elements, maximum } => {
+                write!(f, "Grid with {} elements is too large, maximum is {}.", elements, maximum)
+            }
+            SudokuError::ElementOutOfRange { index, value } => {
+                write!(f, "Invalid element {} in cell {}", value, index)
+            }
+            SudokuError::Contradiction { cells } => {
+                let cells: Vec<String> = cells.iter().map(|i| i.to_string()).collect();
+                write!(f, "Grid contains self-contradictory cells {}.", cells.join(", "))
+            }
+            SudokuError::NoSolution => f.write_str("Grid has no solution, some entry is wrong."),
+            SudokuError::UnknownSymmetry(name) => write!(f, "Unknown symmetry {}", name),
+            SudokuError::InvalidCage { sum, cells } => {
+                let cells: Vec<String> = cells.iter().map(|i| i.to_string()).collect();
+                write!(f, "Invalid cage with sum {} of cells {}.", sum, cells.join(", "))
+            }
+            SudokuError::UnsupportedCages => f.write_str("Dancing Links can't solve grids with cages."),
+            SudokuError::UnknownExtraHouses(name) => write!(f, "Unknown extra houses {}", name),
+            SudokuError::UnsupportedExtraHouses(extra) => {
+                write!(f, "Grids with irregular regions can't have {}.", extra)
+            }
+        }
+    }
+}
+
+impl Error for SudokuError {}
+
+/// Returns a mask with the bits of all elements `1..=elements` set.
+fn full_mask(elements: SizeType) -> MaskType {
+    if elements >= MaskType::BITS as SizeType {
+        MaskType::MAX
+    } else {
+        (1 << elements) - 1
+    }
+}
+
+/// Returns the bit representing element `value` in a mask, or no
+/// bit for empty cells and elements too large for a mask.
+fn element_bit(value: ElementType) -> MaskType {
+    match value {
+        0 => 0,
+        _ => (1 as MaskType).checked_shl(value as u32 - 1).unwrap_or(0),
+    }
+}
+
+/// Returns the highest element in a non-empty mask.
+fn highest_element(mask: MaskType) -> ElementType {
+    (MaskType::BITS - mask.leading_zeros()) as ElementType
+}
+
+/// Returns the union of the sets of `count` different elements from
+/// `allowed` that add up to `sum`, or `None` if there is no such set.
+fn sum_combinations(allowed: MaskType, count: SizeType, sum: SizeType) -> Option<MaskType> {
+    if count == 0 {
+        return (sum == 0).then_some(0);
+    }
+    let available = allowed.count_ones() as SizeType;
+    if available < count {
+        return None;
+    }
+    let lowest: SizeType = mask_elements(allowed).take(count).map(SizeType::from).sum();
+    let highest: SizeType = mask_elements(allowed).skip(available - count).map(SizeType::from).sum();
+    if sum < lowest || sum > highest {
+        return None;
+    }
+    // Sets with the highest element allowed, and those without it.
+    let top = highest_element(allowed);
+    let bit = element_bit(top);
+    let with = sum
+        .checked_sub(SizeType::from(top))
+        .and_then(|left| sum_combinations(allowed & !bit, count - 1, left))
+        .map(|mask| mask | bit);
+    let without = sum_combinations(allowed & !bit, count, sum);
+    match (with, without) {
+        (None, None) => None,
+        (with, without) => Some(with.unwrap_or(0) | without.unwrap_or(0)),
+    }
+}
+
+/// Iterates over the elements in a mask, lowest first.
+fn mask_elements(mask: MaskType) -> impl Iterator<Item = ElementType> {
+    let mut mask = mask;
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let value = mask.trailing_zeros() as ElementType + 1;
+        mask &= mask - 1;
+        Some(value)
+    })
+}
+
+/// How `SudokuSolver` picks the empty cell to guess a value for
+/// when nothing more can be deduced.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CellOrder {
+    /// The first empty cell, starting with the top-left cell and
+    /// continuing row by row.
+    Fixed,
+
+    /// The empty cell with the fewest possible values, also known as
+    /// minimum remaining values. Ties go to the first such cell.
+    #[default]
+    MostConstrained,
+
+    /// Like `MostConstrained`, but with the number of possible values
+    /// divided by how often the houses of the cell led to contradictions
+    /// so far, so that guesses go where the search keeps failing. Ties
+    /// go to the first such cell.
+    Weighted,
+}
+
+/// Options that change how `SudokuSolver` searches, but not which
+/// solutions it finds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SolverOptions {
+    pub cell_order: CellOrder,
+
+    /// If set, the values of a guessed cell are tried in random order
+    /// using this seed, instead of highest first.
+    pub shuffle_seed: Option<u64>,
+}
+
+/// Tries to match cell `c` to one of its `candidates`, moving other
+/// cells to other values if needed, as in Kuhn's algorithm. `matched`
+/// has the cell each value is matched to, and `visited` the values
+/// already tried.
+fn augment(c: SizeType, candidates: &[MaskType], matched: &mut [SizeType], visited: &mut MaskType) -> bool {
+    for v in mask_elements(candidates[c] & !*visited) {
+        *visited |= element_bit(v);
+        let other = matched[v as SizeType];
+        if other == SizeType::MAX || augment(other, candidates, matched, visited) {
+            matched[v as SizeType] = c;
+            return true;
+        }
+    }
+    false
+}
+
+/// Every place where a row or column crosses a box in two or more cells,
+/// given the cells of the `houses` and what each of them is.
+fn intersections(houses: &[Vec<SizeType>], labels: &[House]) -> Vec<Intersection> {
+    let mut intersections = Vec::new();
+    for (l, line) in houses.iter().enumerate().filter(|&(h, _)| labels[h].is_line()) {
+        for (b, boxed) in houses.iter().enumerate().filter(|&(h, _)| !labels[h].is_line()) {
+            let cells: Vec<SizeType> = line.iter().copied().filter(|i| boxed.contains(i)).collect();
+            if cells.len() < 2 {
+                continue;
+            }
+            intersections.push(Intersection {
+                line: l,
+                boxed: b,
+                line_rest: line.iter().copied().filter(|i| !cells.contains(i)).collect(),
+                box_rest: boxed.iter().copied().filter(|i| !cells.contains(i)).collect(),
+                cells,
+            });
+        }
+    }
+    intersections
+}
+
+/// A point in the search where a cell was given a guessed value,
+/// so that the search can come back and try the other values.
+struct Branch {
+    // The cell that was guessed.
+    index: SizeType,
+
+    // Values not yet tried in the cell.
+    untried: MaskType,
+
+    // Length of the trail before the guess; everything after it is
+    // undone before trying the next value.
+    trail_len: SizeType,
+}
+
+/// Something the solver did to the grid that back-tracking undoes.
+enum Change {
+    // The cell at the index was filled in.
+    Assigned(SizeType),
+
+    // These values were ruled out in the cell at the index.
+    Eliminated(SizeType, MaskType),
+}
+
+/// Where a row or column crosses a box in two or more cells. If a value
+/// fits only there within one of the two houses, it can't go elsewhere
+/// in the other; these are locked candidates.
+struct Intersection {
+    // Indexes into `houses` of the row or column, and of the box.
+    line: SizeType,
+    boxed: SizeType,
+
+    cells: Vec<SizeType>,
+
+    // The cells of the row or column outside the box.
+    line_rest: Vec<SizeType>,
+
+    // The cells of the box outside the row or column.
+    box_rest: Vec<SizeType>,
+}
+
 pub struct SudokuSolver {
     grid: SudokuGrid,
 
-    // Index at which possibilities should be considered. If value is `>= self.grid.size` then the grid is full.
-    next_index: Option<SizeType>,
+    options: SolverOptions,
+
+    // Decides which value to try next, if `options.shuffle_seed` is set.
+    random: Option<Random>,
+
+    // The cells of every row, column and box, computed once.
+    houses: Vec<Vec<SizeType>>,
+
+    // Next follows some data at every house ("this house").
+
+    // Elements already placed in this house.
+    house_used: Vec<MaskType>,
+
+    // One more than the times this house turned out contradictory.
+    house_weight: Vec<u64>,
+
+    // Set when a cell of this house changed since it was last checked
+    // for locked candidates and unmatchable values.
+    house_dirty: Vec<bool>,
+
+    // Where rows and columns cross boxes, computed once.
+    intersections: Vec<Intersection>,
+
+    // Next follows some data at every cage of `grid` ("this cage").
+
+    // Elements already placed in this cage.
+    cage_used: Vec<MaskType>,
 
-    // Indexes that should be returned to when all possiblities has been exhausted at the current index.
-    index_stack: Vec<SizeType>,
+    // Set when a cell of this cage changed since it was last checked
+    // for values not adding up to the sum.
+    cage_dirty: Vec<bool>,
 
     // Next follows some data at every index ("this cell").
 
-    // Points to index of next empty cell after this cell.
-    index_of_next_empty: Vec<SizeType>,
+    // Indexes into `houses` of the houses this cell belongs to.
+    cell_houses: Vec<Vec<SizeType>>,
 
-    // Possible elements to try out in this cell.
-    possibles: Vec<Option<Vec<ElementType>>>,
+    // Index into the cages of `grid` of the cage this cell is in, if any.
+    cell_cage: Vec<Option<SizeType>>,
+
+    // Values ruled out in this cell other than by its houses.
+    eliminated: Vec<MaskType>,
+
+    // What the solver did, in order, so it can be undone when
+    // back-tracking.
+    trail: Vec<Change>,
+
+    // Guesses that still have values left to try, innermost last.
+    branches: Vec<Branch>,
+
+    // Set when the first call to `next` has propagated the original grid.
+    started: bool,
+
+    // Set when all possibilities have been exhausted.
+    done: bool,
 }
 
 impl SudokuSolver {
     pub fn new(grid: SudokuGrid) -> SudokuSolver {
-        let size = grid.size;
-        let mut index_of_next_empty = vec![0; size];
-        let mut ne = size; // Next empty cell index.
-        for i in (0..size).rev() {
-            // Point to the next empty cell from here.
-            index_of_next_empty[i] = ne;
-            if grid.cells[i] == 0 { ne = i; }
+        SudokuSolver::with_options(grid, SolverOptions::default())
+    }
+
+    pub fn with_options(grid: SudokuGrid, options: SolverOptions) -> SudokuSolver {
+        let houses = grid.houses();
+        let mut house_used = vec![0; houses.len()];
+        let mut cell_houses = vec![Vec::new(); grid.size];
+        for (h, house) in houses.iter().enumerate() {
+            for &index in house {
+                house_used[h] |= element_bit(grid.cells[index]);
+                cell_houses[index].push(h);
+            }
+        }
+        let intersections = intersections(&houses, &grid.house_labels());
+        let mut cage_used = vec![0; grid.cages.len()];
+        let mut cell_cage = vec![None; grid.size];
+        for (c, cage) in grid.cages.iter().enumerate() {
+            for &index in &cage.cells {
+                cage_used[c] |= element_bit(grid.cells[index]);
+                cell_cage[index] = Some(c);
+            }
+        }
+        let trail = Vec::with_capacity(grid.size);
+        SudokuSolver {
+            cage_dirty: vec![true; cage_used.len()],
+            cage_used,
+            cell_cage,
+            eliminated: vec![0; grid.size],
+            grid,
+            options,
+            random: options.shuffle_seed.map(Random::new),
+            houses,
+            house_weight: vec![1; house_used.len()],
+            house_dirty: vec![true; house_used.len()],
+            house_used,
+            intersections,
+            cell_houses,
+            trail,
+            branches: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Possible values for the empty cell at `index`.
+    fn candidates(&self, index: SizeType) -> MaskType {
+        let mut used = 0;
+        for &h in &self.cell_houses[index] {
+            used |= self.house_used[h];
+        }
+        if let Some(c) = self.cell_cage[index] {
+            used |= self.cage_used[c];
+        }
+        full_mask(self.grid.elements) & !used & !self.eliminated[index]
+    }
+
+    /// Fills in `value` at `index`, remembering to undo it later.
+    fn assign(&mut self, index: SizeType, value: ElementType) {
+        self.grid.cells[index] = value;
+        let bit = element_bit(value);
+        for &h in &self.cell_houses[index] {
+            self.house_used[h] |= bit;
+            self.house_dirty[h] = true;
+        }
+        if let Some(c) = self.cell_cage[index] {
+            self.cage_used[c] |= bit;
+            self.cage_dirty[c] = true;
+        }
+        self.trail.push(Change::Assigned(index));
+    }
+
+    /// Rules out the values in `mask` in the empty cell at `index`,
+    /// remembering to undo it later. Returns `true` if any of them were
+    /// still possible.
+    fn eliminate(&mut self, index: SizeType, mask: MaskType) -> bool {
+        let mask = mask & self.candidates(index);
+        if mask == 0 {
+            return false;
+        }
+        self.eliminated[index] |= mask;
+        for &h in &self.cell_houses[index] {
+            self.house_dirty[h] = true;
+        }
+        if let Some(c) = self.cell_cage[index] {
+            self.cage_dirty[c] = true;
+        }
+        self.trail.push(Change::Eliminated(index, mask));
+        true
+    }
+
+    /// Undoes everything done after the trail had length `trail_len`.
+    fn undo(&mut self, trail_len: SizeType) {
+        while self.trail.len() > trail_len {
+            match self.trail.pop().unwrap() {
+                Change::Assigned(index) => {
+                    let bit = element_bit(self.grid.cells[index]);
+                    for &h in &self.cell_houses[index] {
+                        self.house_used[h] &= !bit;
+                    }
+                    if let Some(c) = self.cell_cage[index] {
+                        self.cage_used[c] &= !bit;
+                    }
+                    self.grid.cells[index] = 0;
+                }
+                Change::Eliminated(index, mask) => self.eliminated[index] &= !mask,
+            }
         }
-        let index_stack = Vec::with_capacity(size);
+    }
 
-        // next_index shall start at first non-empty.
-        let mut next_index = None;
-        if !grid.cells.is_empty() {
-            match grid.cells[0] {
-                // If the first cell is empty point to it.
-                0 => { next_index = Some(0); }
-                // But if it isn't we know the next empty one.
-                _ => { next_index = Some(index_of_next_empty[0]); }
+    /// Union of the possible values of the empty cells among `cells`.
+    fn union_of(&self, cells: &[SizeType]) -> MaskType {
+        cells
+            .iter()
+            .filter(|&&i| self.grid.cells[i] == 0)
+            .fold(0, |mask, &i| mask | self.candidates(i))
+    }
+
+    /// Rules out every value of a cell in `house` that leaves no way to
+    /// fit the other missing values in the other empty cells, found by
+    /// matching cells to values; this covers naked and hidden subsets of
+    /// any size. Returns `None` if there is no way at all, else whether
+    /// anything was ruled out.
+    fn eliminate_unmatchable(&mut self, house: SizeType) -> Option<bool> {
+        let cells: Vec<SizeType> = self.houses[house]
+            .iter()
+            .copied()
+            .filter(|&i| self.grid.cells[i] == 0)
+            .collect();
+        let candidates: Vec<MaskType> = cells.iter().map(|&i| self.candidates(i)).collect();
+        // The cell, by position in `cells`, that each value is matched to.
+        let mut matched = vec![SizeType::MAX; self.grid.elements + 1];
+        for c in 0..cells.len() {
+            let mut visited = 0;
+            if !augment(c, &candidates, &mut matched, &mut visited) {
+                return None;
+            }
+        }
+        // Cell `c` reaches cell `d` if it can take the value of `d`, which
+        // then has to go elsewhere, and so on.
+        let mut reach: Vec<MaskType> = candidates
+            .iter()
+            .enumerate()
+            .map(|(c, &mask)| mask_elements(mask).fold(1 << c, |r, v| r | 1 << matched[v as SizeType]))
+            .collect();
+        for k in 0..cells.len() {
+            for c in 0..cells.len() {
+                if reach[c] & (1 << k) != 0 {
+                    reach[c] |= reach[k];
+                }
             }
         }
+        let mut progress = false;
+        for c in 0..cells.len() {
+            // A value fits if it lets the cell it was matched to take
+            // another value, and so on back to this cell.
+            let unfit = mask_elements(candidates[c])
+                .filter(|&v| reach[matched[v as SizeType]] & (1 << c) == 0)
+                .fold(0, |mask, v| mask | element_bit(v));
+            progress |= self.eliminate(cells[c], unfit);
+        }
+        Some(progress)
+    }
+
+    /// Rules out every value of an empty cell in cage `c` that can't be
+    /// part of a set of different values adding up to the sum of the cage
+    /// with the elements already placed, given what could go in the other
+    /// empty cells. Returns `None` if no value fits some cell or the cage
+    /// is full but doesn't add up, else whether anything was ruled out.
+    fn eliminate_by_sum(&mut self, c: SizeType) -> Option<bool> {
+        let cage = &self.grid.cages[c];
+        let cells: Vec<SizeType> = cage.cells.iter().copied().filter(|&i| self.grid.cells[i] == 0).collect();
+        let placed_sum: SizeType = mask_elements(self.cage_used[c]).map(SizeType::from).sum();
+        let left = cage.sum.checked_sub(placed_sum)?;
+        let candidates: Vec<MaskType> = cells.iter().map(|&i| self.candidates(i)).collect();
+        if cells.is_empty() {
+            return (left == 0).then_some(false);
+        }
+        let mut progress = false;
+        for k in 0..cells.len() {
+            let others = (0..cells.len()).filter(|&j| j != k).fold(0, |mask, j| mask | candidates[j]);
+            let fit = mask_elements(candidates[k])
+                .filter(|&v| {
+                    SizeType::from(v) <= left
+                        && sum_combinations(others & !element_bit(v), cells.len() - 1, left - SizeType::from(v)).is_some()
+                })
+                .fold(0, |mask, v| mask | element_bit(v));
+            if fit == 0 {
+                return None;
+            }
+            progress |= self.eliminate(cells[k], candidates[k] & !fit);
+        }
+        Some(progress)
+    }
+
+    /// Rules out locked candidates: values that within a row, column or
+    /// box only fit where it crosses another of them, so can't go in the
+    /// rest of that other house. Returns `true` if anything was ruled out.
+    fn eliminate_locked(&mut self) -> bool {
+        let mut progress = false;
+        for k in 0..self.intersections.len() {
+            let intersection = &self.intersections[k];
+            if !self.house_dirty[intersection.line] && !self.house_dirty[intersection.boxed] {
+                continue;
+            }
+            let inside = self.union_of(&intersection.cells);
+            let line_rest = self.union_of(&intersection.line_rest);
+            let box_rest = self.union_of(&intersection.box_rest);
+            // Pointing: only in the intersection within the box.
+            let pointing = inside & !box_rest & line_rest;
+            // Claiming: only in the intersection within the line.
+            let claiming = inside & !line_rest & box_rest;
+            if pointing | claiming == 0 {
+                continue;
+            }
+            let intersection = &self.intersections[k];
+            let targets: Vec<(SizeType, MaskType)> = intersection
+                .line_rest
+                .iter()
+                .map(|&i| (i, pointing))
+                .chain(intersection.box_rest.iter().map(|&i| (i, claiming)))
+                .filter(|&(i, mask)| mask != 0 && self.grid.cells[i] == 0)
+                .collect();
+            for (i, mask) in targets {
+                progress |= self.eliminate(i, mask);
+            }
+        }
+        progress
+    }
+
+    /// Fills in every cell that follows from the current grid without
+    /// guessing, until nothing more can be deduced:
+    ///
+    /// * a naked single is an empty cell with only one possible value;
+    /// * a hidden single is a value that fits in only one cell of a
+    ///   row, column or box.
+    ///
+    /// When there are no singles it rules out values instead, first
+    /// those that don't add up to the sum of their cage, then locked
+    /// candidates and then values that leave no way to fill a house, and
+    /// looks for singles again. Large grids need that to be solved in
+    /// reasonable time.
+    ///
+    /// Returns `false` if the grid turned out to be contradictory, that
+    /// is an empty cell without possible values, a house where a missing
+    /// value fits nowhere or a cage that can't add up.
+    fn propagate(&mut self) -> bool {
+        let full = full_mask(self.grid.elements);
+        let mut progress = true;
+        while progress {
+            progress = false;
+
+            // Naked singles.
+            for index in 0..self.grid.size {
+                if self.grid.cells[index] != 0 {
+                    continue;
+                }
+                let candidates = self.candidates(index);
+                if candidates == 0 {
+                    for &h in &self.cell_houses[index] {
+                        self.house_weight[h] += 1;
+                    }
+                    return false;
+                }
+                if candidates.count_ones() == 1 {
+                    self.assign(index, highest_element(candidates));
+                    progress = true;
+                }
+            }
+
+            // Hidden singles.
+            for h in 0..self.houses.len() {
+                // Elements possible in at least one, and in at least two,
+                // empty cells of the house.
+                let mut once: MaskType = 0;
+                let mut twice: MaskType = 0;
+                for &index in &self.houses[h] {
+                    if self.grid.cells[index] == 0 {
+                        let candidates = self.candidates(index);
+                        twice |= once & candidates;
+                        once |= candidates;
+                    }
+                }
+                let missing = full & !self.house_used[h];
+                if missing & !once != 0 {
+                    self.house_weight[h] += 1;
+                    return false;
+                }
+                let mut hidden = missing & !twice;
+                while hidden != 0 {
+                    let value = highest_element(hidden);
+                    let bit = element_bit(value);
+                    hidden &= !bit;
+                    let place = self.houses[h]
+                        .iter()
+                        .copied()
+                        .find(|&i| self.grid.cells[i] == 0 && self.candidates(i) & bit != 0);
+                    // An earlier placement in this house may have taken
+                    // the only place; the next round will notice.
+                    if let Some(index) = place {
+                        self.assign(index, value);
+                        progress = true;
+                    }
+                }
+            }
+
+            if !progress {
+                for c in 0..self.cage_used.len() {
+                    if !self.cage_dirty[c] {
+                        continue;
+                    }
+                    self.cage_dirty[c] = false;
+                    match self.eliminate_by_sum(c) {
+                        None => return false,
+                        Some(eliminated) => progress |= eliminated,
+                    }
+                }
+            }
+            if !progress {
+                progress = self.eliminate_locked();
+            }
+            if !progress {
+                for h in 0..self.houses.len() {
+                    if !self.house_dirty[h] {
+                        continue;
+                    }
+                    self.house_dirty[h] = false;
+                    match self.eliminate_unmatchable(h) {
+                        None => {
+                            self.house_weight[h] += 1;
+                            return false;
+                        }
+                        Some(eliminated) => progress |= eliminated,
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the index of an empty cell to guess a value for, or
+    /// `None` if the grid is full.
+    fn choose_empty(&self) -> Option<SizeType> {
+        match self.options.cell_order {
+            CellOrder::Fixed => self.grid.cells.iter().position(|&x| x == 0),
+            CellOrder::MostConstrained => (0..self.grid.size)
+                .filter(|&index| self.grid.cells[index] == 0)
+                .min_by_key(|&index| self.candidates(index).count_ones()),
+            CellOrder::Weighted => {
+                let mut best = None;
+                // More than any cell can have, and small enough not to overflow.
+                let (mut best_count, mut best_weight) = (MAX_ELEMENTS as u64 + 1, 1);
+                for index in 0..self.grid.size {
+                    if self.grid.cells[index] != 0 {
+                        continue;
+                    }
+                    let count = self.candidates(index).count_ones() as u64;
+                    let weight: u64 = self.cell_houses[index].iter().map(|&h| self.house_weight[h]).sum();
+                    if count * best_weight < best_count * weight {
+                        best = Some(index);
+                        (best_count, best_weight) = (count, weight);
+                    }
+                }
+                best
+            }
+        }
+    }
+
+    /// Tries the next untried value of the innermost guess, back-tracking
+    /// to outer guesses whenever a guess runs out of values. Returns
+    /// `false` when there is nothing left to try.
+    fn advance(&mut self) -> bool {
+        while let Some(branch) = self.branches.last_mut() {
+            let index = branch.index;
+            let trail_len = branch.trail_len;
+            let untried = branch.untried;
+            if untried == 0 {
+                self.undo(trail_len);
+                self.branches.pop();
+                continue;
+            }
+            let value = match &mut self.random {
+                None => highest_element(untried),
+                Some(random) => {
+                    // Drop a random number of the lowest values, then
+                    // take the lowest remaining.
+                    let mut rest = untried;
+                    for _ in 0..random.below(untried.count_ones() as SizeType) {
+                        rest &= rest - 1;
+                    }
+                    rest.trailing_zeros() as ElementType + 1
+                }
+            };
+            branch.untried &= !element_bit(value);
+            self.undo(trail_len);
+            self.assign(index, value);
+            if self.propagate() {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl SudokuSolver {
+    /// Fills in the grid with the next solution. Returns `false` if
+    /// there are no more solutions.
+    fn find_next(&mut self) -> bool {
+        if self.done {
+            return false;
+        }
+        let alive = if self.started {
+            // The previous call found a solution, continue with
+            // the other possibilities.
+            self.advance()
+        } else {
+            self.started = true;
+            // 0x0 grids have no solutions.
+            self.grid.size > 0 && self.propagate()
+        };
+        if !alive {
+            self.done = true;
+            return false;
+        }
+        loop {
+            match self.choose_empty() {
+                None => {
+                    // All cells filled, so we have a solution.
+                    return true;
+                }
+                Some(index) => {
+                    let untried = self.candidates(index);
+                    let trail_len = self.trail.len();
+                    self.branches.push(Branch { index, untried, trail_len });
+                    if !self.advance() {
+                        self.done = true;
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for SudokuSolver {
+    type Item = SudokuGrid;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.find_next() {
+            Some(self.grid.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Counts the remaining solutions without making a copy of each.
+    fn count(mut self) -> usize {
+        let mut count = 0;
+        while self.find_next() {
+            count += 1;
+        }
+        count
+    }
+}
+
+/// Returns an iterator which will provide the solutions.
+pub fn solutions(grid: &SudokuGrid) -> Result<SudokuSolver, SudokuError> {
+    solutions_with_options(grid, SolverOptions::default())
+}
+
+/// Like `solutions`, but lets the caller choose how the solver searches.
+pub fn solutions_with_options(
+    grid: &SudokuGrid,
+    options: SolverOptions,
+) -> Result<SudokuSolver, SudokuError> {
+    check(grid)?;
+    Ok(SudokuSolver::with_options(grid.clone(), options))
+}
+
+/// Counts the solutions without making a copy of each, stopping
+/// at `limit` if given.
+pub fn count_solutions(grid: &SudokuGrid, limit: Option<usize>) -> Result<usize, SudokuError> {
+    check(grid)?;
+    let mut solver = SudokuSolver::new(grid.clone());
+    let mut count = 0;
+    while limit.is_none_or(|limit| count < limit) && solver.find_next() {
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Whether a grid has no, one or several solutions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Uniqueness {
+    NoSolution,
+
+    /// The one and only solution.
+    Unique(SudokuGrid),
+
+    /// The first two solutions found; there may be more.
+    Multiple(SudokuGrid, SudokuGrid),
+}
 
-        SudokuSolver {
-            grid,
-            next_index,
-            index_stack,
-            index_of_next_empty,
-            possibles: vec![None; size],
+impl Uniqueness {
+    /// Indexes of the cells where the two solutions of `Multiple`
+    /// differ. A given in one of them rules out one of the two, though
+    /// not necessarily other solutions. Empty for the other cases.
+    pub fn ambiguous_cells(&self) -> Vec<usize> {
+        match self {
+            Uniqueness::Multiple(first, second) => (0..first.size)
+                .filter(|&i| first.cells[i] != second.cells[i])
+                .collect(),
+            _ => Vec::new(),
         }
     }
 }
 
-impl Iterator for SudokuSolver {
-    type Item = SudokuGrid;
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.next_index {
-            None => {
-                // Only 0x0 grids end up here.
-                None
-            }
-            Some(x) => {
-                // For rest of function x works like an index into the cells.
-
-                // If x is past the end of the cells all the cells have
-                // been filled, so we have a solution.
-                if x >= self.grid.size {
-                    // Return the solution and continue to
-                    // other possibilities.
-                    self.next_index = self.index_stack.pop();
-                    return Some(self.grid.clone());
-                }
-                let mut x = x;
-                while x < self.grid.size {
-                    // This cell is empty in the original grid.
-
-                    // If we have not visited this cell before
-                    // we now need to get possible values at x.
-                    let g = &self.grid;
-                    let possibles_at_x =
-                        self.possibles[x].get_or_insert_with({ ||
-                            g.possibilities(x) });
-
-                    match possibles_at_x.pop() {
-                        Some(p) => {
-                            // Try setting cell to value...
-                            self.grid.cells[x] = p;
-                            // ...remembering to come back here when done...
-                            self.index_stack.push(x);
-                            // ...but right now, check if we get anywhere
-                            // with the next empty cell.
-                            x = self.index_of_next_empty[x];
-                        }
-                        None => {
-                            // We are done visiting this cell; clean up.
-                            self.grid.cells[x] = 0;
-                            self.possibles[x] = None;
-                            // Back-track to a previous cell if any.
-                            match self.index_stack.pop() {
-                                None => { return None; }
-                                Some(ni) => { x = ni; }
-                            }
-                        }
-                    }
-                }
-                // Will only come here if x >= self.grid.size, which
-                // means we could return grid as solution here, but
-                // instead call self recursively once to keep the
-                // success code in one place.
-                self.next_index = Some(x); // Remember x when we recurse.
-                self.next()
-            }
-        }
-    }
+/// Finds out if the grid has exactly one solution, looking no further
+/// than the second solution. Givens contradicting each other make for
+/// no solution rather than an error.
+pub fn check_unique(grid: &SudokuGrid) -> Result<Uniqueness, SudokuError> {
+    let mut solutions = match solutions(grid) {
+        Err(SudokuError::Contradiction { .. }) => return Ok(Uniqueness::NoSolution),
+        result => result?,
+    };
+    Ok(match (solutions.next(), solutions.next()) {
+        (None, _) => Uniqueness::NoSolution,
+        (Some(first), None) => Uniqueness::Unique(first),
+        (Some(first), Some(second)) => Uniqueness::Multiple(first, second),
+    })
 }
 
-/// Returns an iterator which will provide the solutions.
-pub fn solutions(grid: &SudokuGrid) -> Result<SudokuSolver, String> {
-    let grid = grid.clone();
-    // Check grid for self-contradictions.
+/// Checks grid for invalid elements and self-contradictions.
+fn check(grid: &SudokuGrid) -> Result<(), SudokuError> {
+    let mut contradictions = Vec::new();
     for i in 0..grid.size {
         let x = grid.cells[i];
         if x == 0 {
             continue;
         }
         if x as SizeType > grid.elements {
-            return Err(format!("Invalid element {}", x));
+            return Err(SudokuError::ElementOutOfRange { index: i, value: x });
         }
-        let possibles = grid.possibilities(i);
-        if !possibles.contains(&x) {
-            return Err("Grid contains self-contradictory cell.".to_string());
+        if grid.candidates(i) & element_bit(x) == 0 {
+            contradictions.push(i);
         }
     }
-    Ok(SudokuSolver::new(grid))
+    match contradictions.is_empty() {
+        true => Ok(()),
+        false => Err(SudokuError::Contradiction { cells: contradictions }),
+    }
 }
 
-/// Returns a string that is useful for output on the console.
+/// Options that change how `format_with_options` writes a grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Put a '*' after every cell filled in by a solver, so it can be
+    /// told apart from the givens. `parse` ignores the marks.
+    pub mark_filled: bool,
+}
+
+/// Returns a string that is useful for output on the console. Grids
+/// with up to 35 elements are written with a character per cell, larger
+/// ones with numbers separated by spaces. Grids with irregular regions
+/// start with their region map and have lines drawn around the regions.
+/// Grids with cages start with a line for each cage and have lines
+/// drawn around the cages instead, with the sum of each written above it.
 pub fn format(grid: SudokuGrid) -> String {
+    format_with_options(grid, FormatOptions::default())
+}
+
+/// Like `format`, but lets the caller choose how cells are written.
+pub fn format_with_options(grid: SudokuGrid, options: FormatOptions) -> String {
     if grid.size == 0 { return "".to_string(); }
-    let mut result = String::with_capacity(16*16*3);
+    if !grid.cages.is_empty() {
+        return format_cages(&grid, options);
+    }
+    if let Some(regions) = &grid.regions {
+        return format_regions(&grid, regions, &[], options);
+    }
+    let number_width = grid.elements.to_string().len();
+    let mut result = String::with_capacity(grid.size * (number_width + 3));
+    result.push_str(&format_header(&grid));
     let minus_one_mod_row_size = grid.elements - 1;
-    let minus_one_mod_boxsize = grid.boxsize - 1;
-    let row_of_boxes_count = grid.boxsize * grid.elements;
+    let minus_one_mod_box_width = grid.box_width - 1;
+    let row_of_boxes_count = grid.box_height * grid.elements;
     for i in 0..grid.size {
         if i > 0 && i % row_of_boxes_count == 0 {
              // Empty line before next row of boxes unless first row.
              result.push('\n');
         }
-        let value = &grid.cells[i];
-        result.push_str(&format_element(*value));
+        result.push_str(&format_cell(&grid, i));
+        let last_on_line = i % grid.elements == minus_one_mod_row_size;
+        let marked = options.mark_filled && grid.is_filled_by_solver(i);
+        if marked {
+            result.push('*');
+        }
+        if options.mark_filled && !marked && !last_on_line {
+            // Every cell takes the same width so columns line up.
+            result.push(' ');
+        }
         // After the formatted cell value we add one of:
-        if i % grid.elements == minus_one_mod_row_size {
+        if last_on_line {
             result.push('\n'); // after last cell on line
-        } else if i % grid.boxsize == minus_one_mod_boxsize {
+        } else if i % grid.box_width == minus_one_mod_box_width {
             result.push_str("  "); // extra space after box
         } else {
             result.push(' '); // to separate from cell after
@@ -290,32 +1381,346 @@ pub fn format(grid: SudokuGrid) -> String {
     result
 }
 
+/// Writes a grid with irregular regions, with lines of '|', '-' and '+'
+/// around every region, in between the cells. Lines between rows with
+/// no region ending there are left out. Each of `labels` is written at
+/// the start of the line above the cell at its index.
+fn format_regions(grid: &SudokuGrid, regions: &[SizeType], labels: &[(SizeType, String)], options: FormatOptions) -> String {
+    let n = grid.elements;
+    let longest_label = labels.iter().map(|(_, label)| label.len()).max().unwrap_or(0);
+    let cell_width = longest_label.max(format_cell(grid, 0).len() + options.mark_filled as SizeType);
+    // Whether there is a line left of, or above, the cell at the row and
+    // column, which may be one past the last.
+    let line_left = |row: SizeType, col: SizeType| {
+        col == 0 || col == n || regions[row * n + col - 1] != regions[row * n + col]
+    };
+    let line_above = |row: SizeType, col: SizeType| {
+        row == 0 || row == n || regions[(row - 1) * n + col] != regions[row * n + col]
+    };
+    let mut result = format_header(grid);
+    for row in 0..=n {
+        let mut between = String::new();
+        for col in 0..=n {
+            let vertical = (row > 0 && line_left(row - 1, col)) || (row < n && line_left(row, col));
+            let horizontal = (col > 0 && line_above(row, col - 1)) || (col < n && line_above(row, col));
+            between.push(match (vertical, horizontal) {
+                (false, false) => ' ',
+                (true, false) => '|',
+                (false, true) => '-',
+                (true, true) => '+',
+            });
+            if col < n {
+                let fill = if line_above(row, col) { '-' } else { ' ' };
+                let label = labels.iter().find(|(i, _)| *i == row * n + col).map_or("", |(_, label)| label);
+                between.push_str(label);
+                between.extend(std::iter::repeat_n(fill, cell_width - label.len()));
+            }
+        }
+        if between.contains('-') {
+            result.push_str(&between);
+            result.push('\n');
+        }
+        if row == n {
+            break;
+        }
+        for col in 0..n {
+            let i = row * n + col;
+            result.push(if line_left(row, col) { '|' } else { ' ' });
+            let cell = format_cell(grid, i);
+            result.push_str(&cell);
+            if options.mark_filled {
+                result.push(if grid.is_filled_by_solver(i) { '*' } else { ' ' });
+            }
+            let written = cell.len() + options.mark_filled as SizeType;
+            result.extend(std::iter::repeat_n(' ', cell_width - written));
+        }
+        result.push_str("|\n");
+    }
+    result
+}
+
+/// Writes `grid` with the outlines of its cages, and the sum of each
+/// cage on the line above its first cell. Cells in no cage are outlined
+/// one by one.
+fn format_cages(grid: &SudokuGrid, options: FormatOptions) -> String {
+    let mut ids: Vec<SizeType> = (grid.cages.len()..grid.cages.len() + grid.size).collect();
+    let mut labels = Vec::with_capacity(grid.cages.len());
+    for (c, cage) in grid.cages.iter().enumerate() {
+        for &i in &cage.cells {
+            ids[i] = c;
+        }
+        labels.push((*cage.cells.iter().min().unwrap(), cage.sum.to_string()));
+    }
+    format_regions(grid, &ids, &labels, options)
+}
+
+/// The element in the cell at `index` as `format` writes it: a
+/// character, or for large grids a number as wide as the largest.
+fn format_cell(grid: &SudokuGrid, index: SizeType) -> String {
+    let value = grid.cells[index];
+    if grid.elements <= MAX_CHAR_ELEMENTS {
+        return format_element(value);
+    }
+    let number = match value {
+        0 => ".".to_string(),
+        _ => value.to_string(),
+    };
+    format!("{:>width$}", number, width = grid.elements.to_string().len())
+}
+
+/// The lines declaring the layout for `parse`, and an empty line after
+/// them: the region map if the grid has irregular regions, or a line
+/// like "boxes 2x3" if the boxes aren't of the usual shape, a line like
+/// "extra diagonals" if it has extra houses, and a line like
+/// "cage 10 r1c1 r1c2" for every cage. Nothing if none of these are
+/// needed.
+fn format_header(grid: &SudokuGrid) -> String {
+    let mut header = String::new();
+    if let Some(regions) = &grid.regions {
+        header.push_str("regions\n");
+        for row in regions.chunks(grid.elements) {
+            header.extend(row.iter().map(|&region| REGION_NAMES[region] as char));
+            header.push('\n');
+        }
+    } else if default_boxes(grid.elements) != Ok((grid.box_width, grid.box_height)) {
+        header.push_str(&format!("boxes {}x{}\n", grid.box_width, grid.box_height));
+    }
+    if !grid.extra.is_empty() {
+        let names: Vec<String> = grid.extra.iter().map(|extra| extra.to_string()).collect();
+        header.push_str(&format!("extra {}\n", names.join(" ")));
+    }
+    let n = grid.elements;
+    for cage in &grid.cages {
+        let cells: Vec<String> = cage.cells.iter().map(|i| format!("r{}c{}", i / n + 1, i % n + 1)).collect();
+        header.push_str(&format!("cage {} {}\n", cage.sum, cells.join(" ")));
+    }
+    if !header.is_empty() {
+        header.push('\n');
+    }
+    header
+}
+
 /// Parses some input as a Sudoku puzzle grid.
 /// Characters '0' and '.' are interpeted as empty cells.
 /// '1' to '9', 'A' to 'Z', and 'a' to 'z' as different elements.
 /// Other characters are ignored.
 ///
-/// The number of values must be a perfect square squared.
+/// For grids with more elements than there are characters the input
+/// can instead be numbers separated by spaces or commas, like
+/// "12 3 . 64", with '.' or 0 for empty cells. Input with a comma in it
+/// is read that way, other words being ignored. So is input of only
+/// numbers and '.', if some number has several digits and reading it
+/// that way gives a grid. A '*' after a number, as
+/// `format_with_options` may write, is ignored.
+///
+/// The number of values must be a perfect square.
 /// The maximum value must not be greater than the square
 /// root of the number of values.
 ///
+/// Boxes have the usual shape for the number of elements, see
+/// `SudokuGrid::new`. To use another shape start with a line like
+/// "boxes 2x3", for boxes 2 columns wide and 3 rows high.
+///
+/// For irregular regions, as in jigsaw sudoku, start with a line
+/// "regions" followed by the region map: a line for every row with a
+/// character for every cell, the same character for cells in the same
+/// region, like "AAABBBCCC". Lines of '|', '-' and '+' drawn by `format`
+/// are ignored.
+///
+/// Extra houses, as of `variants::ExtraHouses`, are given by a line
+/// like "extra diagonals windows" before the grid.
+///
+/// The cages of a Killer sudoku are given by a line for each, like
+/// "cage 15 r1c1 r1c2 r2c1" for a cage with sum 15 of the cells at row 1,
+/// column 1 and so on, counting from 1. With cages, lines with a '-' in
+/// them are ignored, as `format` writes the sums of cages in those.
+///
 /// Typically you'd input 81 dots and numbers between 1 and 9,
 /// 9 on each row.
-pub fn parse(content: &str) -> Result<SudokuGrid, String> {
-    // 256 is enough for a 16*16 grid.
-    let mut cell_values = Vec::with_capacity(256);
-    for c in content.chars() {
-        let value = parse_element(c);
-        if let Some(x) = value {
-            cell_values.push(x);
+pub fn parse(content: &str) -> Result<SudokuGrid, SudokuError> {
+    let (header, mut content) = split_header(content)?;
+    let body: String;
+    if !header.cages.is_empty() {
+        body = content.lines().filter(|line| !line.contains('-')).collect::<Vec<_>>().join("\n");
+        content = &body;
+    }
+    let load = |cell_values: &[ElementType]| load_with_header(cell_values, &header);
+    let (numbers, only_numbers) = parse_numbers(content);
+    if content.contains(',') {
+        return load(&numbers);
+    }
+    if only_numbers && numbers.iter().any(|&x| x >= 10) {
+        // Could still be a character per cell, like "769 412 853".
+        if let Ok(grid) = load(&numbers) {
+            return Ok(grid);
+        }
+    }
+    let cell_values: Vec<ElementType> = content.chars().filter_map(parse_element).collect();
+    load(&cell_values)
+}
+
+/// Reads the numbers separated by commas, whitespace or '|' in
+/// `content`, with '.' for 0, and tells if there were no other words
+/// than those and lines of '-' and '+'. Numbers too
+/// large for an element become the largest element value, for
+/// `SudokuGrid::load` to reject.
+fn parse_numbers(content: &str) -> (Vec<ElementType>, bool) {
+    let mut numbers = Vec::new();
+    let mut only_numbers = true;
+    let words = content
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '|')
+        .map(|word| word.trim_end_matches('*'))
+        .filter(|word| !word.is_empty() && !word.chars().all(|c| c == '-' || c == '+'));
+    for word in words {
+        match word {
+            "." => numbers.push(0),
+            _ if word.chars().all(|c| c.is_ascii_digit()) => {
+                numbers.push(word.parse().unwrap_or(ElementType::MAX))
+            }
+            _ => only_numbers = false,
+        }
+    }
+    (numbers, only_numbers)
+}
+
+/// How a grid is divided into boxes or regions, as the first lines of
+/// the input to `parse` may declare.
+enum Layout {
+    /// Boxes of this width and height.
+    Boxes(BoxShape),
+
+    /// The region of every cell.
+    Regions(Vec<SizeType>),
+}
+
+/// What the first lines of the input to `parse` declare.
+#[derive(Default)]
+struct Header {
+    /// The boxes or regions, if not the usual boxes.
+    layout: Option<Layout>,
+
+    extra: Vec<ExtraHouses>,
+
+    cages: Vec<CageLine>,
+}
+
+/// A cage as a line of the input to `parse` declares it.
+struct CageLine {
+    line: String,
+
+    sum: SizeType,
+
+    /// The row and column of every cell, numbered from zero.
+    cells: Vec<(SizeType, SizeType)>,
+}
+
+/// Loads `cell_values` with the boxes or regions and the extra houses
+/// of `header`.
+fn load_with_header(cell_values: &[ElementType], header: &Header) -> Result<SudokuGrid, SudokuError> {
+    let mut grid = match &header.layout {
+        None => SudokuGrid::load(cell_values)?,
+        Some(Layout::Boxes((box_width, box_height))) => SudokuGrid::load_with_boxes(cell_values, *box_width, *box_height)?,
+        Some(Layout::Regions(regions)) => SudokuGrid::load_with_regions(cell_values, regions)?,
+    };
+    for &extra in &header.extra {
+        grid.add_extra_houses(extra)?;
+    }
+    let n = grid.elements;
+    for cage in &header.cages {
+        if cage.cells.iter().any(|&(row, col)| row >= n || col >= n) {
+            return Err(SudokuError::InvalidHeader(cage.line.clone()));
+        }
+        let cells: Vec<SizeType> = cage.cells.iter().map(|&(row, col)| row * n + col).collect();
+        grid.add_cage(cage.sum, &cells)?;
+    }
+    Ok(grid)
+}
+
+/// Splits off first lines like "boxes 2x3", "regions" and a region
+/// map, "extra diagonals windows" or "cage 10 r1c1 r1c2" from `content`,
+/// returning what they declare and the rest.
+fn split_header(content: &str) -> Result<(Header, &str), SudokuError> {
+    let mut header = Header::default();
+    let mut content = content;
+    loop {
+        let trimmed = content.trim_start();
+        let (line, rest) = trimmed.split_once('\n').unwrap_or((trimmed, ""));
+        let line = line.trim();
+        let invalid = || SudokuError::InvalidHeader(line.to_string());
+        if line == "regions" {
+            let (regions, rest) = split_regions(rest)?;
+            header.layout = Some(Layout::Regions(regions));
+            content = rest;
+        } else if let Some(cage) = line.strip_prefix("cage") {
+            let mut words = cage.split_whitespace();
+            let sum = words.next().and_then(|word| word.parse().ok()).ok_or_else(invalid)?;
+            let cells = words.map(parse_cell).collect::<Option<Vec<_>>>().ok_or_else(invalid)?;
+            header.cages.push(CageLine { line: line.to_string(), sum, cells });
+            content = rest;
+        } else if let Some(names) = line.strip_prefix("extra") {
+            for name in names.split_whitespace() {
+                header.extra.push(name.parse()?);
+            }
+            content = rest;
+        } else if let Some(shape) = line.strip_prefix("boxes") {
+            let (width, height) = shape.trim().split_once('x').ok_or_else(invalid)?;
+            let width = width.trim().parse().map_err(|_| invalid())?;
+            let height = height.trim().parse().map_err(|_| invalid())?;
+            header.layout = Some(Layout::Boxes((width, height)));
+            content = rest;
+        } else {
+            return Ok((header, content));
+        }
+    }
+}
+
+/// Reads a cell like "r3c5", numbered from one, as its row and column
+/// numbered from zero.
+fn parse_cell(word: &str) -> Option<(SizeType, SizeType)> {
+    let (row, col) = word.strip_prefix('r')?.split_once('c')?;
+    let (row, col): (SizeType, SizeType) = (row.parse().ok()?, col.parse().ok()?);
+    Some((row.checked_sub(1)?, col.checked_sub(1)?))
+}
+
+/// Splits off the region map from `content`: a line for every row with
+/// a character for every cell, whitespace aside, and as many rows as
+/// the first has cells. Returns the region of every cell, numbered in
+/// order of first appearance, and the rest.
+fn split_regions(content: &str) -> Result<(Vec<SizeType>, &str), SudokuError> {
+    let mut names: Vec<char> = Vec::new();
+    let mut regions = Vec::new();
+    let mut width = None;
+    let mut rows = 0;
+    let mut rest = content;
+    while width.is_none_or(|width| rows < width) {
+        if rest.is_empty() {
+            return Err(SudokuError::InvalidHeader("regions".to_string()));
+        }
+        let (line, after) = rest.split_once('\n').unwrap_or((rest, ""));
+        rest = after;
+        let row: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+        if row.is_empty() {
+            continue;
+        }
+        if *width.get_or_insert(row.len()) != row.len() {
+            return Err(SudokuError::InvalidHeader(line.trim().to_string()));
         }
+        for name in row {
+            let region = names.iter().position(|&n| n == name).unwrap_or_else(|| {
+                names.push(name);
+                names.len() - 1
+            });
+            regions.push(region);
+        }
+        rows += 1;
     }
-    SudokuGrid::load(&cell_values)
+    Ok((regions, rest))
 }
 
 /// Convert element value to string representation. 0 becomes ".",
 /// 1 to 9 becomes "1" to "9", 10 to 35 becomes "A" to "Z",
-/// 36 to 61 becomes "a" to "z".
+/// 36 to 61 becomes "a" to "z", and larger ones their number, which
+/// `parse_element` can't read back.
 fn format_element(n: ElementType) -> String {
     let n = n as u32;
     match n {
@@ -329,9 +1734,7 @@ fn format_element(n: ElementType) -> String {
             .unwrap()
             .to_ascii_lowercase()
             .to_string(),
-        _ => {
-            panic!("don't know how to format that")
-        }
+        _ => n.to_string(),
     }
 }
 
@@ -458,10 +1861,227 @@ mod parse_format {
 
     #[test]
     fn given_1x1_grid_format_shall_return_1_as_string() {
-        let grid = SudokuGrid::load(&vec![1]).unwrap();
+        let grid = SudokuGrid::load(&[1]).unwrap();
         let result = format(grid);
         assert_eq!("1\n", result);
     }
+
+    #[test]
+    fn given_wrong_length_load_should_fail_with_invalid_length() {
+        let result = SudokuGrid::load(&[0; 80]);
+        assert_eq!(Some(SudokuError::InvalidLength(80)), result.err());
+    }
+
+    #[test]
+    fn given_256x256_grid_load_should_fail_with_too_large() {
+        let result = SudokuGrid::load(&vec![0; 65536]);
+        let error = SudokuError::TooLarge { elements: 256, maximum: MAX_ELEMENTS };
+        assert_eq!(Some(error), result.err());
+        assert!(SudokuGrid::load(&vec![0; 65537]).is_err());
+    }
+
+    #[test]
+    fn given_element_above_elements_load_should_fail_with_out_of_range() {
+        let result = parse("1 2 3 4  . . . .  . . . 9  . . . .");
+        let error = SudokuError::ElementOutOfRange { index: 11, value: 9 };
+        assert_eq!(Some(error), result.err());
+    }
+
+    #[test]
+    fn given_7_elements_new_should_fail_with_invalid_elements() {
+        assert_eq!(Some(SudokuError::InvalidElements(7)), SudokuGrid::new(7).err());
+        assert_eq!(Some(SudokuError::TooLarge { elements: 81, maximum: 64 }), SudokuGrid::new(81).err());
+    }
+
+    #[test]
+    fn given_6x6_grid_format_should_separate_boxes_3_wide_and_2_high() {
+        let grid = parse("123456 456123 231564 564231 312645 645312").unwrap();
+        assert_eq!(
+            "1 2 3  4 5 6\n4 5 6  1 2 3\n\n\
+             2 3 1  5 6 4\n5 6 4  2 3 1\n\n\
+             3 1 2  6 4 5\n6 4 5  3 1 2\n",
+            format(grid)
+        );
+    }
+
+    #[test]
+    fn given_boxes_header_parse_should_use_that_shape_and_format_should_write_it() {
+        let grid = parse(&format!("boxes 2x3\n12 34 56\n{}", ".".repeat(30))).unwrap();
+        assert_eq!((2, 3), (grid.box_width, grid.box_height));
+        let result = format(grid);
+        assert!(result.starts_with("boxes 2x3\n\n1 2  3 4  5 6\n"), "{}", result);
+        let read = parse(&result).unwrap();
+        assert_eq!((2, 3), (read.box_width, read.box_height));
+    }
+
+    #[test]
+    fn given_box_shape_overflowing_load_with_boxes_should_fail() {
+        let error = SudokuError::InvalidBoxes { box_width: usize::MAX, box_height: 2, elements: 2 };
+        assert_eq!(Some(error), SudokuGrid::load_with_boxes(&[1, 2, 2, 1], usize::MAX, 2).err());
+    }
+
+    #[test]
+    fn given_boxes_header_not_fitting_parse_should_fail() {
+        let error = SudokuError::InvalidBoxes { box_width: 4, box_height: 2, elements: 6 };
+        assert_eq!(Some(error), parse(&format!("boxes 4x2\n{}", ".".repeat(36))).err());
+        assert!(matches!(parse("boxes three by two\n."), Err(SudokuError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn given_boxes_header_too_large_to_multiply_parse_should_fail() {
+        let error = SudokuError::InvalidBoxes { box_width: usize::MAX, box_height: 2, elements: 2 };
+        assert_eq!(Some(error), parse("boxes 18446744073709551615x2\n12\n21\n").err());
+        let wrapping = parse("boxes 4611686018427387905x4\n1234 3412 2143 4321");
+        assert!(matches!(wrapping, Err(SudokuError::InvalidBoxes { elements: 4, .. })), "{:?}", wrapping);
+    }
+
+    #[test]
+    fn given_36x36_grid_format_should_write_numbers_and_parse_should_read_them_back() {
+        let grid = crate::generate::generate_grid(36, 1).unwrap();
+        let result = format(grid.clone());
+        assert!(result.lines().next().unwrap().split_whitespace().all(|w| w.parse::<u8>().is_ok()), "{}", result);
+        assert_eq!(grid.cells, parse(&result).unwrap().cells);
+    }
+
+    #[test]
+    fn given_numbers_separated_by_spaces_or_commas_parse_should_read_numbers() {
+        let text: String = (0..25).map(|i| format!("{} ", (i % 25) + 1)).collect();
+        let grid = parse(&format!("{}{}", text, ". ".repeat(600))).unwrap();
+        assert_eq!(25, grid.elements);
+        assert_eq!(25, grid.cells[24]);
+        let commas = format!("{}{}", text.replace(' ', ","), ".,".repeat(600));
+        assert_eq!(grid.cells, parse(&commas).unwrap().cells);
+        // Single digits per cell are still read a character at a time.
+        assert_eq!(4, parse("12 34 34 12 21 43 43 21").unwrap().elements);
+    }
+
+    #[test]
+    fn given_regions_header_parse_should_use_regions_and_format_should_draw_them() {
+        let text = "regions\nAAAB\nACBB\nCCDB\nCDDD\n\n";
+        let grid = parse(&format!("{}1... ..3. .... .2..", text)).unwrap();
+        assert_eq!(vec![0, 1, 2, 4], (0..16).filter(|&i| grid.region(i) == 0).collect::<Vec<_>>());
+        let result = format(grid.clone());
+        assert_eq!(
+            format!(
+                "{}+-----+-+\n|1 . .|.|\n| +-+-+ |\n|.|.|3 .|\n+-+ +-+ |\n\
+                 |. .|.|.|\n| +-+ +-+\n|.|2 . .|\n+-+-----+\n",
+                text
+            ),
+            result
+        );
+        let read = parse(&result).unwrap();
+        assert_eq!((grid.cells, grid.regions), (read.cells, read.regions));
+    }
+
+    #[test]
+    fn given_region_without_a_cell_per_element_load_with_regions_should_fail() {
+        let regions = [0, 0, 0, 1, 0, 2, 1, 1, 2, 2, 3, 1, 2, 3, 3, 1];
+        let error = SudokuError::InvalidRegion { region: 1, cells: 5, elements: 4 };
+        assert_eq!(Some(error), SudokuGrid::load_with_regions(&[0; 16], &regions).err());
+        assert!(matches!(parse("regions\nAAAB\nACB\n"), Err(SudokuError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn given_extra_header_parse_should_add_houses_and_format_should_write_them() {
+        let grid = parse(&format!("boxes 2x3\nextra diagonals disjoint-groups\n{}", ".".repeat(36))).unwrap();
+        assert_eq!(&[ExtraHouses::Diagonals, ExtraHouses::DisjointGroups], grid.extra_houses());
+        assert_eq!(6 * 3 + 2 + 6, grid.houses().len());
+        let result = format(grid);
+        assert!(result.starts_with("boxes 2x3\nextra diagonals disjoint-groups\n\n. ."), "{}", result);
+        let read = parse(&result).unwrap();
+        assert_eq!(&[ExtraHouses::Diagonals, ExtraHouses::DisjointGroups], read.extra_houses());
+        let error = SudokuError::UnknownExtraHouses("anti-diagonals".to_string());
+        assert_eq!(Some(error), parse("extra anti-diagonals\n1... .... .... ....").err());
+    }
+
+    #[test]
+    fn given_jigsaw_grid_add_extra_houses_should_only_take_diagonals() {
+        let mut grid = parse("regions\nAAAB\nACBB\nCCDB\nCDDD\n1... ..3. .... .2..").unwrap();
+        assert_eq!(Ok(()), grid.add_extra_houses(ExtraHouses::Diagonals));
+        let error = SudokuError::UnsupportedExtraHouses(ExtraHouses::Windows);
+        assert_eq!(Err(error), grid.add_extra_houses(ExtraHouses::Windows));
+        assert!(format(grid).starts_with("regions\nAAAB\nACBB\nCCDB\nCDDD\nextra diagonals\n\n+"));
+    }
+
+    #[test]
+    fn given_cage_header_parse_should_add_cages_and_format_should_draw_them() {
+        let text = "cage 3 r1c1 r1c2\ncage 7 r2c1 r3c1 r3c2\ncage 4 r4c4\n\n";
+        let grid = parse(&format!("{}.... ..3. .... .2..", text)).unwrap();
+        assert_eq!(&[Cage { sum: 3, cells: vec![0, 1] }], &grid.cages()[..1]);
+        let result = format(grid.clone());
+        assert_eq!(
+            format!(
+                "{}+3--+-+-+\n|. .|.|.|\n+7+-+-+-+\n|.|.|3|.|\n| +-+-+-+\n\
+                 |. .|.|.|\n+-+-+-+4+\n|.|2|.|.|\n+-+-+-+-+\n",
+                text
+            ),
+            result
+        );
+        let read = parse(&result).unwrap();
+        assert_eq!((grid.cells, grid.cages), (read.cells, read.cages));
+        assert!(matches!(parse("cage 3 r1c1 r1\n................"), Err(SudokuError::InvalidHeader(_))));
+        assert!(matches!(parse("cage 5 r1c4 r1c5\n................"), Err(SudokuError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn given_cage_that_cannot_add_up_add_cage_should_fail() {
+        let mut grid = SudokuGrid::new(4).unwrap();
+        let invalid = |sum, cells: &[usize]| Err(SudokuError::InvalidCage { sum, cells: cells.to_vec() });
+        assert_eq!(invalid(8, &[0, 1]), grid.add_cage(8, &[0, 1]));
+        assert_eq!(invalid(3, &[0, 0]), grid.add_cage(3, &[0, 0]));
+        assert_eq!(invalid(3, &[15, 16]), grid.add_cage(3, &[15, 16]));
+        assert_eq!(invalid(0, &[]), grid.add_cage(0, &[]));
+        assert_eq!(Ok(()), grid.add_cage(7, &[0, 1]));
+        assert_eq!(invalid(3, &[1, 2]), grid.add_cage(3, &[1, 2]));
+    }
+
+    #[test]
+    fn given_solved_4x4_grid_format_with_mark_filled_should_mark_solver_cells() {
+        let grid = parse("1 . . 4  3 4 1 2\n2 1 4 3  4 3 2 1").unwrap();
+        let solution = solutions(&grid).unwrap().next().unwrap();
+        let options = FormatOptions { mark_filled: true };
+        let result = format_with_options(solution.clone(), options);
+        assert!(result.starts_with("1  2*  3* 4\n"), "{}", result);
+        assert_eq!(solution.cells, parse(&result).unwrap().cells);
+        assert!(!format(solution).contains('*'));
+    }
+}
+
+#[cfg(test)]
+mod masks {
+    use super::*;
+
+    #[test]
+    fn given_9_elements_full_mask_should_have_9_bits_set() {
+        assert_eq!(full_mask(9), 0b1_1111_1111);
+    }
+
+    #[test]
+    fn given_64_elements_full_mask_should_have_all_bits_set() {
+        assert_eq!(full_mask(64), MaskType::MAX);
+    }
+
+    #[test]
+    fn given_empty_cell_element_bit_should_return_no_bit() {
+        assert_eq!(element_bit(0), 0);
+    }
+
+    #[test]
+    fn given_mask_elements_should_return_set_elements_lowest_first() {
+        let mask = element_bit(7) | element_bit(1) | element_bit(64);
+        assert_eq!(mask_elements(mask).collect::<Vec<_>>(), vec![1, 7, 64]);
+        assert_eq!(highest_element(mask), 64);
+    }
+
+    #[test]
+    fn given_allowed_elements_sum_combinations_should_return_elements_adding_up() {
+        let bits = |elements: &[ElementType]| elements.iter().fold(0, |mask, &x| mask | element_bit(x));
+        assert_eq!(Some(bits(&[1, 2])), sum_combinations(full_mask(9), 2, 3));
+        assert_eq!(Some(bits(&[8, 9])), sum_combinations(full_mask(9), 2, 17));
+        assert_eq!(None, sum_combinations(full_mask(9), 2, 18));
+        assert_eq!(Some(bits(&[2, 3, 4, 6, 7, 8])), sum_combinations(full_mask(9) & !bits(&[1]), 2, 10));
+        assert_eq!(Some(bits(&[1, 2, 4])), sum_combinations(full_mask(9), 3, 7));
+    }
 }
 
 #[cfg(test)]
@@ -570,6 +2190,19 @@ mod solving {
         assert_eq!(answer_key.cells, first_solution.unwrap().cells);
     }
 
+    #[test]
+    fn given_4x4_grid_solution_should_tell_givens_from_filled_cells() {
+        let grid = parse("1 . . 4  . . . .\n. . . .  . . . .").unwrap();
+        assert!(grid.is_given(0));
+        assert!(!grid.is_given(1) && !grid.is_filled_by_solver(1));
+        let solution = solutions(&grid).unwrap().next().unwrap();
+        for i in 0..solution.size {
+            assert_ne!(0, solution.value(i));
+            assert_eq!(grid.value(i) != 0, solution.is_given(i));
+            assert_eq!(grid.value(i) == 0, solution.is_filled_by_solver(i));
+        }
+    }
+
     #[test]
     fn given_contradictory_4x4_grid_should_get_no_solution_iterator() {
         let input = "
@@ -603,6 +2236,78 @@ mod solving {
         assert_eq!(grid.cells, first_solution.unwrap().cells);
     }
 
+    #[test]
+    fn given_6x6_grid_solutions_should_respect_boxes_3_wide_and_2_high() {
+        let solved = "123456 456123 231564 564231 312645 645312";
+        let answer_key = parse(solved).unwrap();
+        assert!(check(&answer_key).is_ok());
+        let grid = parse("1.3.5. .5.1.3 2.1.6. .6.2.1 3.2.4. .4.3.2").unwrap();
+        assert_eq!(vec![2], grid.possibilities(1));
+        let solutions: Vec<SudokuGrid> = solutions(&grid).unwrap().collect();
+        assert!(solutions.iter().all(|solution| check(solution).is_ok()));
+        assert!(solutions.iter().any(|solution| solution.cells == answer_key.cells));
+        // The same grid with boxes 2 wide and 3 high breaks them.
+        let tall = parse(&format!("boxes 2x3\n{}", solved)).unwrap();
+        assert!(check(&tall).is_err());
+    }
+
+    #[test]
+    fn given_jigsaw_grid_solutions_should_respect_regions() {
+        let grid = parse(include_str!("../samples/jigsaw.sudoku")).unwrap();
+        let Uniqueness::Unique(solution) = check_unique(&grid).unwrap() else {
+            panic!("should have one solution");
+        };
+        assert!(is_solution_of(&solution, &grid));
+        for region in 0..9 {
+            let mask = (0..81)
+                .filter(|&i| solution.region(i) == region)
+                .fold(0, |mask, i| mask | element_bit(solution.cells[i]));
+            assert_eq!(full_mask(9), mask, "region {}", region);
+        }
+    }
+
+    #[test]
+    fn given_sudoku_x_grid_should_be_unique_only_with_the_diagonals() {
+        let text = include_str!("../samples/sudoku-x.sudoku");
+        let grid = parse(text).unwrap();
+        let Uniqueness::Unique(solution) = check_unique(&grid).unwrap() else {
+            panic!("should have one solution");
+        };
+        for (first, step) in [(0, 10), (8, 8)] {
+            let mask = (0..9).fold(0, |mask, i| mask | element_bit(solution.cells[first + i * step]));
+            assert_eq!(full_mask(9), mask);
+        }
+        let plain = parse(text.trim_start_matches("extra diagonals")).unwrap();
+        assert!(plain.extra_houses().is_empty());
+        assert_eq!(2, count_solutions(&plain, Some(2)).unwrap());
+    }
+
+    #[test]
+    fn given_windoku_grid_solutions_should_fill_every_window() {
+        let grid = parse(include_str!("../samples/windoku.sudoku")).unwrap();
+        let solution = solutions(&grid).unwrap().next().unwrap();
+        assert!(is_solution_of(&solution, &grid));
+        for corner in [10, 14, 46, 50] {
+            let cells = (0..9).map(|i| corner + (i / 3) * 9 + i % 3);
+            let mask = cells.fold(0, |mask, i| mask | element_bit(solution.cells[i]));
+            assert_eq!(full_mask(9), mask, "window at {}", corner);
+        }
+    }
+
+    #[test]
+    fn given_killer_grid_without_givens_solutions_should_add_up_every_cage() {
+        let grid = parse(include_str!("../samples/killer.sudoku")).unwrap();
+        assert!(grid.cells.iter().all(|&x| x == 0));
+        let Uniqueness::Unique(solution) = check_unique(&grid).unwrap() else {
+            panic!("should have one solution");
+        };
+        assert!(is_solution_of(&solution, &grid));
+        for cage in solution.cages() {
+            let sum: usize = cage.cells.iter().map(|&i| usize::from(solution.cells[i])).sum();
+            assert_eq!(cage.sum, sum, "{:?}", cage.cells);
+        }
+    }
+
     #[test]
     fn given_particular_4x4_grid_should_return_three_solutions() {
         let input = "
@@ -652,11 +2357,171 @@ mod solving {
 
     #[test]
     fn given_1x1_grid_solve_shall_return_1x1_grid_with_1_in_cell() {
-        let grid = SudokuGrid::load(&vec![0]).unwrap();
+        let grid = SudokuGrid::load(&[0]).unwrap();
         let mut solutions_vec: Vec<SudokuGrid> =
             solutions(&grid).unwrap().collect();
         assert_eq!(1, solutions_vec.len());
         let the_solution = solutions_vec.pop().unwrap();
         assert_eq!(vec![1], the_solution.cells);
     }
+
+    /// True if `solution` is full, breaks no rule and keeps every
+    /// given element of `grid`.
+    fn is_solution_of(solution: &SudokuGrid, grid: &SudokuGrid) -> bool {
+        (0..grid.size).all(|i| {
+            let x = solution.cells[i];
+            x != 0
+                && solution.possibilities(i).contains(&x)
+                && (grid.cells[i] == 0 || grid.cells[i] == x)
+        })
+    }
+
+    #[test]
+    fn given_hard_16x16_grid_solutions_returns_correct_solution() {
+        let grid = parse(include_str!("../samples/hard-16x16.sudoku")).unwrap();
+        let first_solution = solutions(&grid).unwrap().next().unwrap();
+        assert!(is_solution_of(&first_solution, &grid));
+    }
+
+    #[test]
+    fn given_25x25_puzzle_solutions_returns_correct_solution() {
+        let mut grid = crate::generate::generate_grid(25, 3).unwrap();
+        for i in (0..grid.size).step_by(2) {
+            grid.set_given(i, 0);
+        }
+        let first_solution = solutions(&grid).unwrap().next().unwrap();
+        assert!(is_solution_of(&first_solution, &grid));
+    }
+
+    #[test]
+    fn given_empty_4x4_grid_solutions_returns_all_288_distinct_grids() {
+        let grid = SudokuGrid::new(4).unwrap();
+        let all: Vec<SudokuGrid> = solutions(&grid).unwrap().collect();
+        assert_eq!(288, all.len());
+        assert!(all.iter().all(|s| is_solution_of(s, &grid)));
+        let mut distinct: Vec<&Vec<ElementType>> = all.iter().map(|s| &s.cells).collect();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(288, distinct.len());
+    }
+
+    #[test]
+    fn given_particular_4x4_grid_cell_orders_should_find_same_solutions() {
+        let input = "
+        12..
+        43..
+        ....
+        ...1
+        "
+        .to_string();
+        let grid = parse(&input).unwrap();
+        let mut found = Vec::new();
+        for cell_order in [CellOrder::Fixed, CellOrder::MostConstrained, CellOrder::Weighted] {
+            let options = SolverOptions { cell_order, ..Default::default() };
+            let mut cells: Vec<Vec<ElementType>> = solutions_with_options(&grid, options)
+                .unwrap()
+                .map(|s| s.cells)
+                .collect();
+            cells.sort();
+            found.push(cells);
+        }
+        assert_eq!(3, found[0].len());
+        assert_eq!(found[0], found[1]);
+        assert_eq!(found[0], found[2]);
+    }
+
+    #[test]
+    fn given_contradictions_in_a_row_only_weighted_order_should_guess_there_first() {
+        let grid = SudokuGrid::new(4).unwrap();
+        let mut solvers: Vec<SudokuSolver> = [CellOrder::MostConstrained, CellOrder::Weighted]
+            .into_iter()
+            .map(|cell_order| SudokuSolver::with_options(grid.clone(), SolverOptions { cell_order, ..Default::default() }))
+            .collect();
+        for solver in &mut solvers {
+            // The last row, whose cells all have as many possible values
+            // as any other.
+            solver.house_weight[3] += 2;
+        }
+        assert_eq!(Some(0), solvers[0].choose_empty());
+        assert_eq!(Some(12), solvers[1].choose_empty());
+    }
+
+    #[test]
+    fn given_empty_4x4_grid_count_solutions_should_count_288() {
+        let grid = SudokuGrid::new(4).unwrap();
+        assert_eq!(288, count_solutions(&grid, None).unwrap());
+        assert_eq!(288, solutions(&grid).unwrap().count());
+    }
+
+    #[test]
+    fn given_limit_count_solutions_should_stop_there() {
+        let grid = SudokuGrid::new(4).unwrap();
+        assert_eq!(0, count_solutions(&grid, Some(0)).unwrap());
+        assert_eq!(5, count_solutions(&grid, Some(5)).unwrap());
+        assert_eq!(288, count_solutions(&grid, Some(1000)).unwrap());
+    }
+
+    #[test]
+    fn given_contradictory_4x4_grid_count_solutions_should_fail() {
+        let grid = parse("1234 4321 .2.. ....").unwrap();
+        assert_eq!(
+            Err(SudokuError::Contradiction { cells: vec![1, 9] }),
+            count_solutions(&grid, None)
+        );
+    }
+
+    #[test]
+    fn given_9x9_grid_with_one_solution_check_unique_should_return_unique() {
+        let grid = parse(include_str!("../samples/easy.sudoku")).unwrap();
+        match check_unique(&grid).unwrap() {
+            Uniqueness::Unique(solution) => assert!(is_solution_of(&solution, &grid)),
+            _ => panic!("expected a unique solution"),
+        }
+    }
+
+    #[test]
+    fn given_particular_4x4_grid_check_unique_should_return_two_differing_solutions() {
+        let grid = parse("12.. 43.. .... ...1").unwrap();
+        let uniqueness = check_unique(&grid).unwrap();
+        let ambiguous = uniqueness.ambiguous_cells();
+        match uniqueness {
+            Uniqueness::Multiple(first, second) => {
+                assert!(is_solution_of(&first, &grid));
+                assert!(is_solution_of(&second, &grid));
+                assert!(!ambiguous.is_empty());
+                assert!(ambiguous.iter().all(|&i| grid.cells[i] == 0));
+            }
+            _ => panic!("expected multiple solutions"),
+        }
+    }
+
+    #[test]
+    fn given_unsolvable_4x4_grid_check_unique_should_return_no_solution() {
+        // No contradiction among the givens, but the top-right cell
+        // can't hold anything.
+        let grid = parse("12.. ...3 ...4 ....").unwrap();
+        assert_eq!(Uniqueness::NoSolution, check_unique(&grid).unwrap());
+    }
+
+    #[test]
+    fn given_contradicting_givens_check_unique_should_return_no_solution() {
+        let grid = parse("11.. .... .... ....").unwrap();
+        assert_eq!(Uniqueness::NoSolution, check_unique(&grid).unwrap());
+    }
+
+    #[test]
+    fn given_shuffle_seed_solver_should_find_same_solutions_in_other_order() {
+        let grid = SudokuGrid::new(4).unwrap();
+        let options = SolverOptions { shuffle_seed: Some(99), ..Default::default() };
+        let plain: Vec<Vec<ElementType>> = solutions(&grid).unwrap().map(|s| s.cells).collect();
+        let shuffled: Vec<Vec<ElementType>> = solutions_with_options(&grid, options)
+            .unwrap()
+            .map(|s| s.cells)
+            .collect();
+        assert_ne!(plain, shuffled);
+        let (mut plain, mut shuffled) = (plain, shuffled);
+        plain.sort();
+        shuffled.sort();
+        assert_eq!(plain, shuffled);
+    }
 }
@@ -0,0 +1,349 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Almost Locked Sets: ALS-XZ, ALS-XY-Wing and Death Blossom.
+//!
+//! An almost locked set (ALS) is `k` unsolved cells of a house with
+//! `k + 1` candidates between them. Take away any one candidate and the
+//! rest are locked in the cells. Two sets are joined by a restricted
+//! common candidate x when every cell of one with x sees every cell of
+//! the other with x: at most one of the sets can then hold x.
+
+use std::collections::HashSet;
+
+use super::{combinations, AlmostLockedSet, Cell, Step, Technique};
+use crate::candidates::CandidateGrid;
+use crate::{element_bit, mask_elements, ElementType, MaskType, SizeType};
+
+/// Most cells in a set looked for. Larger sets are rarely needed and
+/// there are many of them in big grids.
+const MAX_CELLS: SizeType = 8;
+
+/// Most groups of cells looked at in a house, enough for sets of any
+/// size in a 9x9 grid. In larger grids only smaller sets are looked for
+/// while houses have many unsolved cells.
+const MAX_GROUPS: SizeType = 512;
+
+/// Most candidates of a stem looked at for a Death Blossom.
+const MAX_STEM_CANDIDATES: u32 = 3;
+
+/// An almost locked set in a grid.
+#[derive(Clone, Debug)]
+pub(super) struct Als {
+    house: SizeType,
+    cells: Vec<SizeType>,
+    candidates: MaskType,
+}
+
+impl Als {
+    /// The cells of the set with `value` as candidate.
+    fn cells_with(&self, grid: &CandidateGrid, value: ElementType) -> Vec<SizeType> {
+        self.cells.iter().copied().filter(|&i| grid.has(i, value)).collect()
+    }
+
+    fn overlaps(&self, other: &Als) -> bool {
+        self.cells.iter().any(|i| other.cells.contains(i))
+    }
+
+    /// Restricted common candidates with `other`, which must not overlap.
+    fn restricted_common(&self, grid: &CandidateGrid, other: &Als) -> MaskType {
+        mask_elements(self.candidates & other.candidates)
+            .filter(|&x| {
+                let theirs = other.cells_with(grid, x);
+                self.cells_with(grid, x)
+                    .iter()
+                    .all(|&a| theirs.iter().all(|&b| grid.sees(a, b)))
+            })
+            .fold(0, |m, x| m | element_bit(x))
+    }
+
+    /// The set as shown in a step.
+    fn public(&self, grid: &CandidateGrid) -> AlmostLockedSet {
+        let n = grid.elements();
+        AlmostLockedSet {
+            house: grid.labels()[self.house],
+            cells: self.cells.iter().map(|&i| Cell::of(i, n)).collect(),
+            candidates: mask_elements(self.candidates).collect(),
+        }
+    }
+}
+
+/// Every almost locked set in the rows, columns and boxes of `grid`.
+/// Sets lying in both a box and a line are only listed once.
+pub(super) fn almost_locked_sets(grid: &CandidateGrid) -> Vec<Als> {
+    let mut found = HashSet::new();
+    let mut sets = Vec::new();
+    for (h, house) in grid.houses().iter().enumerate() {
+        let unsolved: Vec<SizeType> = house.iter().copied().filter(|&i| grid.candidates(i) != 0).collect();
+        // Not the whole house, which holds exactly as many candidates
+        // as cells.
+        for k in 1..unsolved.len().min(max_cells(unsolved.len()) + 1) {
+            for chosen in combinations(unsolved.len(), k) {
+                let candidates = chosen.iter().fold(0, |m, &j| m | grid.candidates(unsolved[j]));
+                if candidates.count_ones() as SizeType != k + 1 {
+                    continue;
+                }
+                let cells: Vec<SizeType> = chosen.iter().map(|&j| unsolved[j]).collect();
+                if found.insert(cells.clone()) {
+                    sets.push(Als { house: h, cells, candidates });
+                }
+            }
+        }
+    }
+    sets
+}
+
+/// Most cells in a set to look for among `unsolved` cells of a house,
+/// so that no more than `MAX_GROUPS` groups of cells are looked at.
+fn max_cells(unsolved: SizeType) -> SizeType {
+    let mut groups = 0;
+    // Ways to choose `k` of the cells.
+    let mut ways: SizeType = 1;
+    for k in 1..=MAX_CELLS.min(unsolved) {
+        ways = ways * (unsolved + 1 - k) / k;
+        groups += ways;
+        if groups > MAX_GROUPS {
+            return k - 1;
+        }
+    }
+    MAX_CELLS
+}
+
+/// Eliminations of `value` from cells outside `sets` seeing every cell
+/// of the sets that has it.
+fn eliminate_seeing(grid: &CandidateGrid, sets: &[&Als], value: ElementType) -> Vec<(Cell, ElementType)> {
+    let n = grid.elements();
+    let seen: Vec<SizeType> = sets.iter().flat_map(|set| set.cells_with(grid, value)).collect();
+    (0..grid.size())
+        .filter(|&i| {
+            grid.has(i, value)
+                && !sets.iter().any(|set| set.cells.contains(&i))
+                && seen.iter().all(|&s| grid.sees(i, s))
+        })
+        .map(|i| (Cell::of(i, n), value))
+        .collect()
+}
+
+/// Eliminations of every element in `values` as `eliminate_seeing`.
+fn eliminate_all_seeing(grid: &CandidateGrid, sets: &[&Als], values: MaskType) -> Vec<(Cell, ElementType)> {
+    let mut eliminations: Vec<(Cell, ElementType)> = mask_elements(values)
+        .flat_map(|z| eliminate_seeing(grid, sets, z))
+        .collect();
+    eliminations.sort();
+    eliminations
+}
+
+/// Finds two sets A and B joined by a restricted common candidate x.
+/// One of them is locked without x, so any other element z they share
+/// is in one of them, and can't be in cells seeing all their z.
+pub(super) fn xz(grid: &CandidateGrid) -> Option<Step> {
+    let sets = almost_locked_sets(grid);
+    for (j, a) in sets.iter().enumerate() {
+        for b in &sets[j + 1..] {
+            if a.candidates & b.candidates == 0 || a.overlaps(b) {
+                continue;
+            }
+            for x in mask_elements(a.restricted_common(grid, b)) {
+                let zs = a.candidates & b.candidates & !element_bit(x);
+                let eliminations = eliminate_all_seeing(grid, &[a, b], zs);
+                if !eliminations.is_empty() {
+                    return Some(Step {
+                        eliminations,
+                        sets: vec![a.public(grid), b.public(grid)],
+                        elements: vec![x],
+                        ..Step::new(Technique::AlsXz)
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Finds sets A and B both joined to a set C, by different restricted
+/// common candidates x and y. C can't hold both, so one of A and B is
+/// locked, and an element z they share can't be in cells seeing all
+/// their z.
+pub(super) fn xy_wing(grid: &CandidateGrid) -> Option<Step> {
+    let sets = almost_locked_sets(grid);
+    for c in &sets {
+        // Sets joined to C, with their restricted common candidates.
+        let joined: Vec<(&Als, MaskType)> = sets
+            .iter()
+            .filter(|s| s.candidates & c.candidates != 0 && !s.overlaps(c))
+            .map(|s| (s, s.restricted_common(grid, c)))
+            .filter(|&(_, rcc)| rcc != 0)
+            .collect();
+        for (j, &(a, rcc_a)) in joined.iter().enumerate() {
+            for &(b, rcc_b) in &joined[j + 1..] {
+                if a.candidates & b.candidates == 0 || a.overlaps(b) {
+                    continue;
+                }
+                for x in mask_elements(rcc_a) {
+                    for y in mask_elements(rcc_b & !element_bit(x)) {
+                        let zs = a.candidates & b.candidates & !element_bit(x) & !element_bit(y);
+                        let eliminations = eliminate_all_seeing(grid, &[a, b], zs);
+                        if !eliminations.is_empty() {
+                            return Some(Step {
+                                eliminations,
+                                sets: vec![a.public(grid), c.public(grid), b.public(grid)],
+                                elements: vec![x, y],
+                                ..Step::new(Technique::AlsXyWing)
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Finds a stem cell and for each of its candidates a petal set holding
+/// that element only in cells seeing the stem. Whatever the stem is, that
+/// petal is locked without it, so an element z in every petal but not
+/// the stem is in one of them, and can't be in cells seeing all their z.
+pub(super) fn death_blossom(grid: &CandidateGrid) -> Option<Step> {
+    let n = grid.elements();
+    let sets = almost_locked_sets(grid);
+    for stem in 0..grid.size() {
+        let candidates = grid.candidates(stem);
+        if !(2..=MAX_STEM_CANDIDATES).contains(&candidates.count_ones()) {
+            continue;
+        }
+        // Possible petals for each candidate of the stem.
+        let petals: Vec<(ElementType, Vec<&Als>)> = mask_elements(candidates)
+            .map(|value| {
+                let fitting = sets
+                    .iter()
+                    .filter(|s| {
+                        !s.cells.contains(&stem)
+                            && s.candidates & !candidates != 0
+                            && !s.cells_with(grid, value).is_empty()
+                            && s.cells_with(grid, value).iter().all(|&i| grid.sees(i, stem))
+                    })
+                    .collect();
+                (value, fitting)
+            })
+            .collect();
+        let mut chosen = Vec::new();
+        if let Some(eliminations) = blossom(grid, &petals, &mut chosen, !candidates) {
+            return Some(Step {
+                eliminations,
+                sets: chosen.iter().map(|s: &&Als| s.public(grid)).collect(),
+                pivot: Some(Cell::of(stem, n)),
+                elements: petals.iter().map(|&(value, _)| value).collect(),
+                ..Step::new(Technique::DeathBlossom)
+            });
+        }
+    }
+    None
+}
+
+/// Picks petals for the rest of the stem's candidates, after `chosen`,
+/// keeping them apart and sharing some of the elements in `common`.
+/// Returns the eliminations once all are picked, leaving them in `chosen`.
+fn blossom<'a>(
+    grid: &CandidateGrid,
+    petals: &[(ElementType, Vec<&'a Als>)],
+    chosen: &mut Vec<&'a Als>,
+    common: MaskType,
+) -> Option<Vec<(Cell, ElementType)>> {
+    let Some((_, fitting)) = petals.get(chosen.len()) else {
+        let eliminations = eliminate_all_seeing(grid, chosen, common);
+        return (!eliminations.is_empty()).then_some(eliminations);
+    };
+    for &petal in fitting {
+        let common = common & petal.candidates;
+        if common == 0 || chosen.iter().any(|s| s.overlaps(petal)) {
+            continue;
+        }
+        chosen.push(petal);
+        if let Some(eliminations) = blossom(grid, petals, chosen, common) {
+            return Some(eliminations);
+        }
+        chosen.pop();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::tests::grid_with;
+
+    #[test]
+    fn given_pair_cell_and_set_sharing_restricted_candidate_xz_should_eliminate_z() {
+        let grid = grid_with(&[(0, 0, &[1, 2]), (4, 0, &[1, 3]), (4, 1, &[2, 3])]);
+        let step = xz(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "ALS-XZ: 1 in r1c1 (row 1) / r5c1 r5c2 (row 5) => \
+             r1c2<>2, r2c2<>2, r3c2<>2, r4c1<>2, r6c1<>2"
+        );
+    }
+
+    #[test]
+    fn given_cells_in_a_house_almost_locked_sets_should_find_them_once() {
+        let grid = grid_with(&[(0, 0, &[1, 2]), (4, 0, &[1, 3]), (4, 1, &[2, 3])]);
+        let sets = almost_locked_sets(&grid);
+        let cells: Vec<&[SizeType]> = sets.iter().map(|set| set.cells.as_slice()).collect();
+        assert!(cells.contains(&&[0][..]));
+        assert!(cells.contains(&&[36, 37][..]));
+        assert_eq!(1, cells.iter().filter(|&&c| c == [36, 37]).count());
+        assert!(sets.iter().all(|set| set.candidates.count_ones() as SizeType == set.cells.len() + 1));
+    }
+
+    #[test]
+    fn given_houses_with_more_unsolved_cells_max_cells_should_look_for_smaller_sets() {
+        assert_eq!(MAX_CELLS, max_cells(9));
+        assert_eq!(2, max_cells(16));
+        assert_eq!(1, max_cells(64));
+        // A house with few cells left gets sets of any size.
+        assert_eq!(MAX_CELLS, max_cells(6));
+    }
+
+    #[test]
+    fn given_two_sets_joined_through_third_xy_wing_should_eliminate_z() {
+        let grid = grid_with(&[(0, 0, &[1, 2]), (0, 4, &[1, 3]), (4, 4, &[2, 3])]);
+        let step = xy_wing(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "ALS-XY-Wing: 1,3 in r1c1 (row 1) / r1c5 (row 1) / r5c5 (row 5) => r5c1<>2"
+        );
+    }
+
+    #[test]
+    fn given_two_cell_set_in_the_middle_xy_wing_should_eliminate_z() {
+        // r5c1 and r5c6 hold two of 2, 3 and 4, joined to r5c5 by 4,
+        // which is joined to r1c5 by 1.
+        let grid = grid_with(&[(0, 4, &[1, 2]), (4, 4, &[1, 4]), (4, 5, &[3, 4]), (4, 0, &[2, 3])]);
+        let step = xy_wing(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "ALS-XY-Wing: 1,4 in r1c5 (row 1) / r5c5 (row 5) / r5c1 r5c6 (row 5) => r1c1<>2"
+        );
+    }
+
+    #[test]
+    fn given_two_cell_petal_death_blossom_should_eliminate_shared() {
+        // The petal for 2 of stem r5c1 is r1c5 and r5c5, holding two of
+        // 1, 2 and 7.
+        let grid = grid_with(&[(4, 4, &[1, 2]), (0, 4, &[1, 7]), (4, 0, &[2, 8]), (4, 1, &[7, 8])]);
+        let step = death_blossom(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "death blossom: 2,8 stem r5c1 petals r1c5 r5c5 (column 5) / r5c2 (row 5) => r1c2<>7"
+        );
+    }
+
+    #[test]
+    fn given_stem_with_petal_for_each_candidate_death_blossom_should_eliminate_shared() {
+        let grid = grid_with(&[(4, 4, &[1, 2]), (0, 4, &[1, 7]), (4, 0, &[2, 7])]);
+        let step = death_blossom(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "death blossom: 1,2 stem r5c5 petals r1c5 (row 1) / r5c1 (row 5) => r1c1<>7"
+        );
+    }
+}
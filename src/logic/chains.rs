@@ -0,0 +1,409 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Chains: X-Chains, XY-Chains and Alternating Inference Chains (AICs).
+//!
+//! A node is a candidate, or for grouped chains an element in two or
+//! more cells of a box that also share a row or column. Two nodes are
+//! strongly linked if at least one of them is true: they are the only
+//! places for an element in a house, or the only candidates of a cell.
+//! They are weakly linked if at most one of them is true: they see each
+//! other with the same element, or are candidates of the same cell.
+//!
+//! An AIC starts and ends with a strong link and alternates between
+//! strong and weak links. If its first node is false, the next is true,
+//! the next false, and so on until the last, which is true. So at least
+//! one of the end nodes is true, and anything that conflicts with both
+//! can be eliminated.
+
+use std::collections::{HashMap, VecDeque};
+
+use super::{Cell, Step, Technique};
+use crate::candidates::CandidateGrid;
+use crate::{format_element, mask_elements, ElementType, SizeType};
+
+/// Longest chain looked for, in nodes. Keeps the search fast and the
+/// chains short enough to follow.
+const MAX_NODES: SizeType = 16;
+
+/// Finds an X-Chain: a chain on one element linked through houses only.
+pub(super) fn x_chain(grid: &CandidateGrid) -> Option<Step> {
+    Graph::new(grid, Technique::XChain).search()
+}
+
+/// Finds an XY-Chain: a chain of cells with two candidates each, strong
+/// links inside the cells, starting and ending on the same element.
+pub(super) fn xy_chain(grid: &CandidateGrid) -> Option<Step> {
+    Graph::new(grid, Technique::XYChain).search()
+}
+
+/// Finds an AIC mixing links through houses and inside cells.
+pub(super) fn aic(grid: &CandidateGrid) -> Option<Step> {
+    Graph::new(grid, Technique::AlternatingInferenceChain).search()
+}
+
+/// Finds an AIC which may also use grouped nodes.
+pub(super) fn grouped_aic(grid: &CandidateGrid) -> Option<Step> {
+    Graph::new(grid, Technique::GroupedAlternatingInferenceChain).search()
+}
+
+/// An element in one or more cells, at least one of which holds it if
+/// the node is true.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Node {
+    // Sorted.
+    cells: Vec<SizeType>,
+    value: ElementType,
+}
+
+/// The nodes and links a kind of chain may use.
+struct Graph<'a> {
+    grid: &'a CandidateGrid,
+    technique: Technique,
+    nodes: Vec<Node>,
+    strong: Vec<Vec<SizeType>>,
+    weak: Vec<Vec<SizeType>>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(grid: &'a CandidateGrid, technique: Technique) -> Graph<'a> {
+        let n = grid.elements();
+        let bivalue_only = technique == Technique::XYChain;
+        let in_house = technique != Technique::XYChain;
+        let in_cell = technique != Technique::XChain;
+
+        let mut nodes = Vec::new();
+        for index in 0..grid.size() {
+            let candidates = grid.candidates(index);
+            if bivalue_only && candidates.count_ones() != 2 {
+                continue;
+            }
+            for value in mask_elements(candidates) {
+                nodes.push(Node { cells: vec![index], value });
+            }
+        }
+        if technique == Technique::GroupedAlternatingInferenceChain {
+            nodes.extend(groups(grid));
+        }
+        let ids: HashMap<(Vec<SizeType>, ElementType), SizeType> = nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| ((node.cells.clone(), node.value), id))
+            .collect();
+
+        let mut strong = vec![Vec::new(); nodes.len()];
+        let mut weak = vec![Vec::new(); nodes.len()];
+        let mut by_value = vec![Vec::new(); n + 1];
+        for (id, node) in nodes.iter().enumerate() {
+            by_value[node.value as SizeType].push(id);
+        }
+        for (id, node) in nodes.iter().enumerate() {
+            if in_house {
+                for house in grid.houses() {
+                    if !node.cells.iter().all(|i| house.contains(i)) {
+                        continue;
+                    }
+                    let rest: Vec<SizeType> = house
+                        .iter()
+                        .copied()
+                        .filter(|&i| grid.has(i, node.value) && !node.cells.contains(&i))
+                        .collect();
+                    if let Some(&other) = ids.get(&(rest, node.value)) {
+                        strong[id].push(other);
+                    }
+                }
+            }
+            if in_cell && node.cells.len() == 1 {
+                let index = node.cells[0];
+                for value in mask_elements(grid.candidates(index)) {
+                    if let Some(&other) = ids.get(&(vec![index], value)).filter(|&&o| o != id) {
+                        weak[id].push(other);
+                        if grid.candidates(index).count_ones() == 2 {
+                            strong[id].push(other);
+                        }
+                    }
+                }
+            }
+            // Seeing each other also means the nodes don't overlap, as
+            // cells don't see themselves.
+            for &other in &by_value[node.value as SizeType] {
+                let sees = node
+                    .cells
+                    .iter()
+                    .all(|&a| nodes[other].cells.iter().all(|&b| grid.sees(a, b)));
+                if sees {
+                    weak[id].push(other);
+                }
+            }
+        }
+        Graph {
+            grid,
+            technique,
+            nodes,
+            strong,
+            weak,
+        }
+    }
+
+    /// Returns the step for the shortest chain with eliminations, or
+    /// the first found among equally short ones.
+    fn search(&self) -> Option<Step> {
+        // States are a node and whether the chain makes it true, at
+        // `id * 2 + 1`, or false, at `id * 2`.
+        let states = self.nodes.len() * 2;
+        let mut parent = vec![0; states];
+        let mut length = vec![0; states];
+        // The start node of the search that last reached each state, so
+        // that nothing needs to be reset between searches.
+        let mut reached_from = vec![SizeType::MAX; states];
+        let mut best: Option<(SizeType, Step)> = None;
+        for start in 0..self.nodes.len() {
+            let limit = best.as_ref().map_or(MAX_NODES, |(length, _)| length - 1);
+            if limit < 4 {
+                break;
+            }
+            let targets = self.targets(start);
+            let first = start * 2;
+            reached_from[first] = start;
+            parent[first] = first;
+            length[first] = 1;
+            let mut queue = VecDeque::from([first]);
+            while let Some(state) = queue.pop_front() {
+                let (id, on) = (state / 2, state % 2 == 1);
+                if on && length[state] >= 4 {
+                    let eliminations = self.conclude(start, id, &targets);
+                    if !eliminations.is_empty() {
+                        let mut chain = vec![id];
+                        let mut s = state;
+                        while s != first {
+                            s = parent[s];
+                            chain.push(s / 2);
+                        }
+                        chain.reverse();
+                        best = Some((chain.len(), self.step(&chain, eliminations)));
+                        break;
+                    }
+                }
+                if length[state] >= limit {
+                    continue;
+                }
+                // From a false node a strong link makes the next true,
+                // from a true one a weak link makes the next false.
+                let links = if on { &self.weak[id] } else { &self.strong[id] };
+                for &next in links {
+                    let next_state = next * 2 + !on as SizeType;
+                    if next != start && reached_from[next_state] != start {
+                        reached_from[next_state] = start;
+                        parent[next_state] = state;
+                        length[next_state] = length[state] + 1;
+                        queue.push_back(next_state);
+                    }
+                }
+            }
+        }
+        best.map(|(_, step)| step)
+    }
+
+    /// Cells with the element of node `start` seeing all of its cells:
+    /// the ones an end node with the same element may eliminate from.
+    fn targets(&self, start: SizeType) -> Vec<SizeType> {
+        let node = &self.nodes[start];
+        (0..self.grid.size())
+            .filter(|&i| {
+                self.grid.has(i, node.value) && node.cells.iter().all(|&c| self.grid.sees(i, c))
+            })
+            .collect()
+    }
+
+    /// Eliminations from a chain where `start` or `end` is true.
+    fn conclude(&self, start: SizeType, end: SizeType, targets: &[SizeType]) -> Vec<(Cell, ElementType)> {
+        let n = self.grid.elements();
+        let (a, z) = (&self.nodes[start], &self.nodes[end]);
+        if a.value == z.value {
+            return targets
+                .iter()
+                .copied()
+                .filter(|&i| !z.cells.contains(&i) && z.cells.iter().all(|&c| self.grid.sees(i, c)))
+                .map(|i| (Cell::of(i, n), a.value))
+                .collect();
+        }
+        if self.technique == Technique::XYChain || a.cells.len() > 1 || z.cells.len() > 1 {
+            return Vec::new();
+        }
+        let (ca, cz) = (a.cells[0], z.cells[0]);
+        if ca == cz {
+            // The cell is one of the two, so nothing else.
+            mask_elements(self.grid.candidates(ca))
+                .filter(|&v| v != a.value && v != z.value)
+                .map(|v| (Cell::of(ca, n), v))
+                .collect()
+        } else if self.grid.sees(ca, cz) {
+            // Either end's element in the other cell would make both
+            // ends false.
+            let mut eliminations = Vec::new();
+            if self.grid.has(ca, z.value) {
+                eliminations.push((Cell::of(ca, n), z.value));
+            }
+            if self.grid.has(cz, a.value) {
+                eliminations.push((Cell::of(cz, n), a.value));
+            }
+            eliminations
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn step(&self, chain: &[SizeType], eliminations: Vec<(Cell, ElementType)>) -> Step {
+        let n = self.grid.elements();
+        let mut cells = Vec::new();
+        let mut elements = Vec::new();
+        for &id in chain {
+            let node = &self.nodes[id];
+            for &i in &node.cells {
+                if !cells.contains(&Cell::of(i, n)) {
+                    cells.push(Cell::of(i, n));
+                }
+            }
+            if !elements.contains(&node.value) {
+                elements.push(node.value);
+            }
+        }
+        elements.sort();
+        Step {
+            eliminations,
+            cells,
+            elements,
+            chain: Some(self.eureka(chain)),
+            ..Step::new(self.technique)
+        }
+    }
+
+    /// Writes the chain in Eureka notation, like
+    /// "(1)r1c1=(1)r1c5-(1)r6c5=(1)r6c2". Links inside a cell are
+    /// written together, like "(1=2)r3c4", and groups of cells like
+    /// "r1c23".
+    fn eureka(&self, chain: &[SizeType]) -> String {
+        let n = self.grid.elements();
+        // Links alternate, starting with a strong one.
+        let link = |i: SizeType| if i.is_multiple_of(2) { '=' } else { '-' };
+        let mut text = String::new();
+        let mut i = 0;
+        while i < chain.len() {
+            if i > 0 {
+                text.push(link(i - 1));
+            }
+            let node = &self.nodes[chain[i]];
+            let mut values = format_element(node.value);
+            while i + 1 < chain.len() && node.cells.len() == 1 && self.nodes[chain[i + 1]].cells == node.cells {
+                values.push(link(i));
+                values.push_str(&format_element(self.nodes[chain[i + 1]].value));
+                i += 1;
+            }
+            text.push_str(&format!("({}){}", values, cells_text(&node.cells, n)));
+            i += 1;
+        }
+        text
+    }
+}
+
+/// Grouped nodes: every element with two or more candidates where a box
+/// and a line meet.
+fn groups(grid: &CandidateGrid) -> Vec<Node> {
+    let houses = grid.houses();
+    let labels = grid.labels();
+    let mut groups = Vec::new();
+    let boxes = houses.iter().zip(labels).filter(|(_, label)| !label.is_line());
+    for (boxed, _) in boxes {
+        for (line, _) in houses.iter().zip(labels).filter(|(_, label)| label.is_line()) {
+            let meet: Vec<SizeType> = line.iter().copied().filter(|i| boxed.contains(i)).collect();
+            if meet.len() < 2 {
+                continue;
+            }
+            for value in 1..=grid.elements() as ElementType {
+                let cells: Vec<SizeType> = meet.iter().copied().filter(|&i| grid.has(i, value)).collect();
+                if cells.len() >= 2 {
+                    groups.push(Node { cells, value });
+                }
+            }
+        }
+    }
+    groups
+}
+
+/// Writes cells in one row or column, like "r3c5", "r1c23" or "r45c6".
+fn cells_text(cells: &[SizeType], n: SizeType) -> String {
+    let list = |numbers: Vec<SizeType>| -> String { numbers.iter().map(|x| (x + 1).to_string()).collect() };
+    let first = Cell::of(cells[0], n);
+    if cells.iter().all(|&i| Cell::of(i, n).row == first.row) {
+        format!("r{}c{}", first.row + 1, list(cells.iter().map(|&i| i % n).collect()))
+    } else {
+        format!("r{}c{}", list(cells.iter().map(|&i| i / n).collect()), first.column + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::tests::{grid_with, ones_in_rows};
+
+    #[test]
+    fn given_two_strong_links_on_one_element_x_chain_should_eliminate_seeing_both_ends() {
+        // In row 1 the 1 only fits in c1 and c5, in row 6 in c2 and c5.
+        let grid = ones_in_rows(&[(0, &[0, 4]), (5, &[1, 4])]);
+        let step = x_chain(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "X-Chain: (1)r1c1=(1)r1c5-(1)r6c5=(1)r6c2 => r2c2<>1, r3c2<>1, r4c1<>1, r5c1<>1"
+        );
+    }
+
+    #[test]
+    fn given_chain_of_pairs_xy_chain_should_eliminate_end_element() {
+        let grid = grid_with(&[(0, 0, &[1, 2]), (0, 4, &[2, 3]), (4, 4, &[3, 1])]);
+        assert!(x_chain(&grid).is_none());
+        let step = xy_chain(&grid).unwrap();
+        assert_eq!(step.chain.as_deref(), Some("(1=2)r1c1-(2=3)r1c5-(3=1)r5c5"));
+        assert_eq!(step.eliminations, vec![(Cell { row: 4, column: 0 }, 1)]);
+        assert_eq!(step.elements, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn given_chain_ending_on_other_element_in_same_cell_aic_should_eliminate_the_rest() {
+        // In row 1 the 1 and the 2 only fit in c1 and c5, so r1c1 is 1 if
+        // r1c5 isn't, and 2 if r1c5 is.
+        let mut cells: Vec<(SizeType, SizeType, &[ElementType])> = vec![(0, 0, &[1, 2, 4])];
+        for column in [1, 2, 3, 5, 6, 7, 8] {
+            cells.push((0, column, &[3, 4, 5, 6, 7, 8, 9]));
+        }
+        let grid = grid_with(&cells);
+        assert!(xy_chain(&grid).is_none());
+        let step = aic(&grid).unwrap();
+        assert_eq!(step.to_string(), "AIC: (1)r1c1=(1-2)r1c5=(2)r1c1 => r1c1<>4");
+    }
+
+    #[test]
+    fn given_grouped_strong_link_grouped_aic_should_use_group() {
+        // In column 1 the 1 only fits in r1, r2 and r6; in column 5 only
+        // in r1 and r6.
+        let mut grid = grid_with(&[]);
+        for row in [2, 3, 4, 6, 7, 8] {
+            grid.eliminate(row * 9, 1);
+        }
+        for row in [1, 2, 3, 4, 6, 7, 8] {
+            grid.eliminate(row * 9 + 4, 1);
+        }
+        assert!(aic(&grid).is_none());
+        let step = grouped_aic(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "grouped AIC: (1)r1c5=(1)r6c5-(1)r6c1=(1)r12c1 => r1c2<>1, r1c3<>1"
+        );
+    }
+
+    #[test]
+    fn given_cells_in_one_row_cells_text_should_list_columns_together() {
+        assert_eq!("r3c5", cells_text(&[22], 9));
+        assert_eq!("r1c23", cells_text(&[1, 2], 9));
+        assert_eq!("r45c6", cells_text(&[32, 41], 9));
+    }
+}
@@ -0,0 +1,246 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Fish: X-Wing, Swordfish and Jellyfish, plain, finned and sashimi.
+//!
+//! A fish of size `k` for an element is `k` base lines (rows, say) where
+//! the element only fits within `k` cover lines (columns). Each base line
+//! needs the element somewhere in the cover lines, which uses up all `k`
+//! of them, so the element can be removed from the rest of the cover
+//! lines. Fins are extra candidates in the base lines outside the cover
+//! lines; if they are all in one box, either a fin is true or the fish
+//! is, so cells seeing both the fins and a cover line can be eliminated.
+
+use super::{combinations, Cell, Step, Technique};
+use crate::candidates::CandidateGrid;
+use crate::{ElementType, House, MaskType, SizeType};
+
+/// Finds a fish of size `k` without fins.
+pub(super) fn basic(grid: &CandidateGrid, k: SizeType) -> Option<Step> {
+    search(grid, k, None)
+}
+
+/// Finds a finned fish of size `k`, either sashimi or not.
+pub(super) fn finned(grid: &CandidateGrid, k: SizeType, sashimi: bool) -> Option<Step> {
+    search(grid, k, Some(sashimi))
+}
+
+/// Looks for fish with rows as base lines, then with columns. `finned`
+/// is `None` for fish without fins, else whether to find sashimi ones.
+fn search(grid: &CandidateGrid, k: SizeType, finned: Option<bool>) -> Option<Step> {
+    let n = grid.elements();
+    let max_width = match finned {
+        None => k,
+        // Fins all fit in one box.
+        Some(_) => k + grid.box_width().max(grid.box_height()),
+    };
+    for value in 1..=n as ElementType {
+        for rows_as_base in [true, false] {
+            let fish = Fish { grid, value, rows_as_base };
+            // Bit `p` in `positions[line]` is set if the element fits at
+            // position `p` along the line.
+            let positions: Vec<MaskType> = (0..n).map(|line| fish.positions(line)).collect();
+            let lines: Vec<SizeType> = (0..n)
+                .filter(|&l| (2..=max_width as u32).contains(&positions[l].count_ones()))
+                .collect();
+            for chosen in combinations(lines.len(), k) {
+                let base: Vec<SizeType> = chosen.iter().map(|&j| lines[j]).collect();
+                let union = base.iter().fold(0, |m, &l| m | positions[l]);
+                let width = union.count_ones() as SizeType;
+                let step = match finned {
+                    None if width == k => fish.conclude(&positions, &base, union, None),
+                    Some(sashimi) if width > k && width <= max_width => {
+                        let spread = bits(union);
+                        combinations(spread.len(), k).find_map(|cover| {
+                            let cover = cover.iter().fold(0, |m, &j| m | 1 << spread[j]);
+                            fish.conclude(&positions, &base, cover, Some(sashimi))
+                        })
+                    }
+                    _ => None,
+                };
+                if step.is_some() {
+                    return step;
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Which element a fish is for, and which way round it lies.
+struct Fish<'a> {
+    grid: &'a CandidateGrid,
+    value: ElementType,
+    rows_as_base: bool,
+}
+
+impl Fish<'_> {
+    /// Index of the cell at `position` along base line `line`.
+    fn index(&self, line: SizeType, position: SizeType) -> SizeType {
+        let n = self.grid.elements();
+        match self.rows_as_base {
+            true => line * n + position,
+            false => position * n + line,
+        }
+    }
+
+    /// Positions along base line `line` where the element fits.
+    fn positions(&self, line: SizeType) -> MaskType {
+        (0..self.grid.elements())
+            .filter(|&p| self.grid.has(self.index(line, p), self.value))
+            .fold(0, |m, p| m | 1 << p)
+    }
+
+    /// Label of base line `line`, or of cover line `line` if `cover`.
+    fn label(&self, line: SizeType, cover: bool) -> House {
+        match self.rows_as_base != cover {
+            true => House::Row(line),
+            false => House::Column(line),
+        }
+    }
+
+    /// Returns the step for the fish with `base` lines and `cover` lines
+    /// (as a mask), if it is valid and eliminates anything. `finned` is
+    /// as for `search`.
+    fn conclude(
+        &self,
+        positions: &[MaskType],
+        base: &[SizeType],
+        cover: MaskType,
+        finned: Option<bool>,
+    ) -> Option<Step> {
+        let grid = self.grid;
+        let n = grid.elements();
+        // Every base line must still need one of the cover lines.
+        if base.iter().any(|&l| positions[l] & cover == 0) {
+            return None;
+        }
+        let fins: Vec<SizeType> = base
+            .iter()
+            .flat_map(|&l| bits(positions[l] & !cover).into_iter().map(move |p| (l, p)))
+            .map(|(l, p)| self.index(l, p))
+            .collect();
+        let technique = match finned {
+            None => technique(base.len(), 0),
+            Some(sashimi) => {
+                let is_sashimi = base.iter().any(|&l| (positions[l] & cover).count_ones() == 1);
+                if is_sashimi != sashimi || !in_one_box(grid, &fins) {
+                    return None;
+                }
+                technique(base.len(), if sashimi { 2 } else { 1 })
+            }
+        };
+        let mut eliminations = Vec::new();
+        for p in bits(cover) {
+            for line in (0..n).filter(|l| !base.contains(l)) {
+                let i = self.index(line, p);
+                if grid.has(i, self.value) && fins.iter().all(|&fin| grid.sees(i, fin)) {
+                    eliminations.push((Cell::of(i, n), self.value));
+                }
+            }
+        }
+        if eliminations.is_empty() {
+            return None;
+        }
+        eliminations.sort();
+        let cells = base
+            .iter()
+            .flat_map(|&l| bits(positions[l] & cover).into_iter().map(move |p| (l, p)))
+            .map(|(l, p)| Cell::of(self.index(l, p), n))
+            .collect();
+        Some(Step {
+            eliminations,
+            cells,
+            houses: base.iter().map(|&l| self.label(l, false)).collect(),
+            cover: bits(cover).into_iter().map(|p| self.label(p, true)).collect(),
+            fins: fins.iter().map(|&i| Cell::of(i, n)).collect(),
+            elements: vec![self.value],
+            ..Step::new(technique)
+        })
+    }
+}
+
+/// The technique for a fish of `size`, with `kind` 0 for no fins,
+/// 1 for finned and 2 for sashimi.
+fn technique(size: SizeType, kind: u8) -> Technique {
+    match (size, kind) {
+        (2, 0) => Technique::XWing,
+        (3, 0) => Technique::Swordfish,
+        (_, 0) => Technique::Jellyfish,
+        (2, 1) => Technique::FinnedXWing,
+        (3, 1) => Technique::FinnedSwordfish,
+        (_, 1) => Technique::FinnedJellyfish,
+        (2, _) => Technique::SashimiXWing,
+        (3, _) => Technique::SashimiSwordfish,
+        (_, _) => Technique::SashimiJellyfish,
+    }
+}
+
+/// True if all the cells are in the same box.
+fn in_one_box(grid: &CandidateGrid, cells: &[SizeType]) -> bool {
+    grid.houses()
+        .iter()
+        .zip(grid.labels())
+        .any(|(house, label)| !label.is_line() && cells.iter().all(|i| house.contains(i)))
+}
+
+/// The numbers of the set bits in `mask`, lowest first.
+fn bits(mask: MaskType) -> Vec<SizeType> {
+    (0..MaskType::BITS as SizeType)
+        .filter(|&p| mask & (1 << p) != 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::tests::ones_in_rows;
+
+    #[test]
+    fn given_two_rows_with_1_in_same_columns_basic_should_find_x_wing() {
+        let grid = ones_in_rows(&[(0, &[1, 6]), (4, &[1, 6])]);
+        let step = basic(&grid, 2).unwrap();
+        assert_eq!(step.technique, Technique::XWing);
+        assert_eq!(step.houses, vec![House::Row(0), House::Row(4)]);
+        assert_eq!(step.cover, vec![House::Column(1), House::Column(6)]);
+        assert_eq!(step.eliminations.len(), 14);
+        assert!(step
+            .to_string()
+            .starts_with("X-Wing: 1 in row 1, row 5 / column 2, column 7 => r2c2<>1, r2c7<>1"));
+    }
+
+    #[test]
+    fn given_three_rows_in_three_columns_basic_should_find_swordfish() {
+        let grid = ones_in_rows(&[(0, &[0, 3]), (3, &[3, 6]), (6, &[0, 6])]);
+        assert!(basic(&grid, 2).is_none());
+        let step = basic(&grid, 3).unwrap();
+        assert_eq!(step.technique, Technique::Swordfish);
+        assert_eq!(step.eliminations.len(), 18);
+    }
+
+    #[test]
+    fn given_x_wing_with_fin_finned_should_eliminate_only_in_fin_box() {
+        let grid = ones_in_rows(&[(0, &[1, 6]), (4, &[1, 6, 7])]);
+        assert!(basic(&grid, 2).is_none());
+        let step = finned(&grid, 2, false).unwrap();
+        assert_eq!(step.technique, Technique::FinnedXWing);
+        assert_eq!(step.fins, vec![Cell { row: 4, column: 7 }]);
+        assert_eq!(
+            step.to_string(),
+            "finned X-Wing: 1 in row 1, row 5 / column 2, column 7 with fins r5c8 => \
+             r4c7<>1, r6c7<>1"
+        );
+    }
+
+    #[test]
+    fn given_x_wing_missing_a_corner_finned_should_find_sashimi() {
+        let grid = ones_in_rows(&[(0, &[1, 6]), (4, &[1, 7])]);
+        assert!(finned(&grid, 2, false).is_none());
+        let step = finned(&grid, 2, true).unwrap();
+        assert_eq!(step.technique, Technique::SashimiXWing);
+        assert_eq!(
+            step.eliminations,
+            vec![(Cell { row: 3, column: 6 }, 1), (Cell { row: 5, column: 6 }, 1)]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Locked candidates: an element confined to where two houses overlap.
+
+use super::{Cell, Step, Technique};
+use crate::candidates::CandidateGrid;
+use crate::ElementType;
+
+/// Finds an element that within a box only fits in one row or column.
+pub(super) fn pointing(grid: &CandidateGrid) -> Option<Step> {
+    locked(grid, Technique::LockedCandidatesPointing)
+}
+
+/// Finds an element that within a row or column only fits in one box.
+pub(super) fn claiming(grid: &CandidateGrid) -> Option<Step> {
+    locked(grid, Technique::LockedCandidatesClaiming)
+}
+
+/// Finds an element whose places in one house (the box for pointing,
+/// the line for claiming) all lie in a second house, so it can be
+/// removed from the rest of the second house.
+fn locked(grid: &CandidateGrid, technique: Technique) -> Option<Step> {
+    let n = grid.elements();
+    let houses = grid.houses();
+    let labels = grid.labels();
+    let from_line = technique == Technique::LockedCandidatesClaiming;
+    for a in 0..houses.len() {
+        if labels[a].is_line() != from_line {
+            continue;
+        }
+        for b in 0..houses.len() {
+            if labels[b].is_line() == from_line {
+                continue;
+            }
+            for value in 1..=n as ElementType {
+                let places = grid.places(a, value);
+                if places.is_empty() || !places.iter().all(|i| houses[b].contains(i)) {
+                    continue;
+                }
+                let eliminations: Vec<(Cell, ElementType)> = houses[b]
+                    .iter()
+                    .filter(|&i| !houses[a].contains(i) && grid.has(*i, value))
+                    .map(|&i| (Cell::of(i, n), value))
+                    .collect();
+                if eliminations.is_empty() {
+                    continue;
+                }
+                return Some(Step {
+                    eliminations,
+                    cells: places.iter().map(|&i| Cell::of(i, n)).collect(),
+                    houses: vec![labels[a], labels[b]],
+                    elements: vec![value],
+                    ..Step::new(technique)
+                });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn given_element_in_one_column_of_box_pointing_should_remove_it_from_column() {
+        // In box 1 the 1 must be in column 1, so not in r4c1 through r9c1.
+        let grid = parse(
+            "
+            .23 ... ...
+            .45 ... ...
+            .67 ... ...
+
+            ... ... ...
+            ... ... ...
+            ... ... ...
+
+            ... ... ...
+            ... ... ...
+            ... ... ...
+            ",
+        )
+        .unwrap();
+        let step = pointing(&CandidateGrid::new(&grid)).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "locked candidates (pointing): 1 in r1c1 r2c1 r3c1 (box 1, column 1) => \
+             r4c1<>1, r5c1<>1, r6c1<>1, r7c1<>1, r8c1<>1, r9c1<>1"
+        );
+    }
+
+    #[test]
+    fn given_element_in_one_box_of_row_claiming_should_remove_it_from_box() {
+        // In row 1 the 1 must be in box 1, so not in rows 2 and 3 of it.
+        let grid = parse(
+            "
+            ... 234 567
+            ... ... ...
+            ... ... ...
+
+            ... ... ...
+            ... ... ...
+            ... ... ...
+
+            ... ... ...
+            ... ... ...
+            ... ... ...
+            ",
+        )
+        .unwrap();
+        let step = claiming(&CandidateGrid::new(&grid)).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "locked candidates (claiming): 1 in r1c1 r1c2 r1c3 (row 1, box 1) => \
+             r2c1<>1, r2c2<>1, r2c3<>1, r3c1<>1, r3c2<>1, r3c3<>1"
+        );
+    }
+}
@@ -0,0 +1,816 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Solving the way a person would, one named deduction at a time, so
+//! that every step can be explained.
+//!
+//! Each step either places an element in a cell or removes candidates
+//! from cells. The easiest applicable technique is always used first.
+
+mod als;
+mod chains;
+mod fish;
+mod intersections;
+mod singles;
+mod subsets;
+mod uniqueness;
+mod wings;
+
+use std::fmt;
+
+use crate::candidates::CandidateGrid;
+use crate::{check, count_solutions, format_element, ElementType, House, SizeType, SudokuError, SudokuGrid};
+
+/// A cell, numbered from zero by row and column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cell {
+    pub row: SizeType,
+    pub column: SizeType,
+}
+
+impl Cell {
+    /// The cell at `index` in a grid with `n` rows.
+    fn of(index: SizeType, n: SizeType) -> Cell {
+        Cell {
+            row: index / n,
+            column: index % n,
+        }
+    }
+
+    /// Index of the cell in a grid with `n` rows.
+    fn index(self, n: SizeType) -> SizeType {
+        self.row * n + self.column
+    }
+}
+
+/// Writes the cell numbered from one, like "r3c5".
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "r{}c{}", self.row + 1, self.column + 1)
+    }
+}
+
+/// The named deductions, from easiest to hardest by `score`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Technique {
+    /// The only cell in a house where an element fits.
+    HiddenSingle,
+    /// The only element that fits in a cell.
+    NakedSingle,
+    /// An element that, within a box, only fits in one row or column,
+    /// so it can't go elsewhere in that row or column.
+    LockedCandidatesPointing,
+    /// An element that, within a row or column, only fits in one box,
+    /// so it can't go elsewhere in that box.
+    LockedCandidatesClaiming,
+    /// Two cells in a house with the same two candidates, which can't
+    /// go elsewhere in the house. Likewise for triples and quads.
+    NakedPair,
+    /// An element that in two rows only fits in the same two columns,
+    /// so it can't go elsewhere in those columns; or the same with rows
+    /// and columns swapped.
+    XWing,
+    /// Two elements that only fit in the same two cells of a house, so
+    /// those cells can't hold anything else. Likewise for triples and
+    /// quads.
+    HiddenPair,
+    NakedTriple,
+    /// Like an X-Wing, but with three rows and columns.
+    Swordfish,
+    HiddenTriple,
+    /// A pivot cell with candidates xy seeing two pincer cells with xz
+    /// and yz. Whichever the pivot is, one pincer is z, so z can't be
+    /// in cells seeing both pincers.
+    XYWing,
+    /// Like an XY-Wing, but the pivot also has z as candidate, so z
+    /// can only be eliminated from cells seeing the pivot too.
+    XYZWing,
+    /// Two cells with the same two candidates xy that don't see each
+    /// other, joined by a house where x only fits in two cells, each
+    /// seeing one of them. One of them is y, so y can't be in cells
+    /// seeing both.
+    WWing,
+    /// Four corners of a rectangle in two boxes with the same two
+    /// candidates, three with nothing else, could swap the two elements
+    /// if the fourth held one of them. So it holds neither.
+    UniqueRectangle1,
+    /// A rectangle where the two corners in a row or column with more
+    /// than the pair both have the same one extra candidate, which must
+    /// be in one of them.
+    UniqueRectangle2,
+    /// A rectangle whose two extra corners must hold one element of the
+    /// pair, so can't hold the other.
+    UniqueRectangle4,
+    /// Like type 2, but with diagonal or three extra corners.
+    UniqueRectangle5,
+    /// A rectangle with two diagonal corners only holding the pair, and
+    /// an element of the pair forming an X-Wing on the corners.
+    UniqueRectangle6,
+    /// A rectangle with one corner only holding the pair, whose opposite
+    /// corner can't hold one of the pair.
+    HiddenRectangle,
+    /// A rectangle whose two extra corners act as one cell with their
+    /// extra candidates in a naked subset.
+    UniqueRectangle3,
+    NakedQuad,
+    /// Like an X-Wing, but with four rows and columns.
+    Jellyfish,
+    HiddenQuad,
+    /// All unsolved cells have two candidates but one, which gets the
+    /// element that would otherwise leave the grid with two solutions.
+    BugPlusOne,
+    /// An X-Wing with extra candidates, the fins, in one box of its
+    /// rows. Only cells that also see the fins are eliminated from.
+    FinnedXWing,
+    /// A finned X-Wing that would have too few candidates to be a fish
+    /// at all without the fins.
+    SashimiXWing,
+    FinnedSwordfish,
+    SashimiSwordfish,
+    FinnedJellyfish,
+    SashimiJellyfish,
+    /// A chain on one element, alternating between houses where it only
+    /// fits in two cells and cells that see each other. One end holds
+    /// the element, so cells seeing both ends can't.
+    XChain,
+    /// A chain of cells with two candidates each, where neighboring cells
+    /// see each other and share an element. If the first cell isn't x,
+    /// the last cell is x, so cells seeing both ends can't be x.
+    XYChain,
+    /// A chain mixing the links of X- and XY-Chains, through any cells.
+    AlternatingInferenceChain,
+    /// An AIC where some nodes are groups of cells in a box and a line.
+    GroupedAlternatingInferenceChain,
+    /// Two almost locked sets joined by a restricted common candidate x.
+    /// Another element z they share must be in one of them, so it can't
+    /// be in cells seeing all their z.
+    AlsXz,
+    /// Two almost locked sets each joined to a third by a different
+    /// restricted common candidate, eliminating like ALS-XZ.
+    AlsXyWing,
+    /// A stem cell with, for each candidate, an almost locked set seeing
+    /// it through that element. An element all the sets share must be in
+    /// one of them.
+    DeathBlossom,
+}
+
+impl Technique {
+    /// All techniques in the order they are tried, by `score`.
+    pub const ALL: [Technique; 37] = [
+        Technique::HiddenSingle,
+        Technique::NakedSingle,
+        Technique::LockedCandidatesPointing,
+        Technique::LockedCandidatesClaiming,
+        Technique::NakedPair,
+        Technique::XWing,
+        Technique::HiddenPair,
+        Technique::FinnedXWing,
+        Technique::SashimiXWing,
+        Technique::NakedTriple,
+        Technique::Swordfish,
+        Technique::HiddenTriple,
+        Technique::FinnedSwordfish,
+        Technique::SashimiSwordfish,
+        Technique::XYWing,
+        Technique::XYZWing,
+        Technique::WWing,
+        Technique::UniqueRectangle1,
+        Technique::UniqueRectangle2,
+        Technique::UniqueRectangle4,
+        Technique::UniqueRectangle5,
+        Technique::UniqueRectangle6,
+        Technique::HiddenRectangle,
+        Technique::UniqueRectangle3,
+        Technique::NakedQuad,
+        Technique::Jellyfish,
+        Technique::HiddenQuad,
+        Technique::FinnedJellyfish,
+        Technique::SashimiJellyfish,
+        Technique::BugPlusOne,
+        Technique::XChain,
+        Technique::XYChain,
+        Technique::AlternatingInferenceChain,
+        Technique::GroupedAlternatingInferenceChain,
+        Technique::AlsXz,
+        Technique::AlsXyWing,
+        Technique::DeathBlossom,
+    ];
+
+    /// True for techniques that are only right if the grid has exactly
+    /// one solution.
+    pub fn assumes_uniqueness(self) -> bool {
+        matches!(
+            self,
+            Technique::UniqueRectangle1
+                | Technique::UniqueRectangle2
+                | Technique::UniqueRectangle3
+                | Technique::UniqueRectangle4
+                | Technique::UniqueRectangle5
+                | Technique::UniqueRectangle6
+                | Technique::HiddenRectangle
+                | Technique::BugPlusOne
+        )
+    }
+
+    /// Looks for a step using this technique.
+    fn find(self, grid: &CandidateGrid) -> Option<Step> {
+        match self {
+            Technique::HiddenSingle => singles::hidden_single(grid),
+            Technique::NakedSingle => singles::naked_single(grid),
+            Technique::LockedCandidatesPointing => intersections::pointing(grid),
+            Technique::LockedCandidatesClaiming => intersections::claiming(grid),
+            Technique::NakedPair => subsets::naked(grid, 2),
+            Technique::XWing => fish::basic(grid, 2),
+            Technique::HiddenPair => subsets::hidden(grid, 2),
+            Technique::NakedTriple => subsets::naked(grid, 3),
+            Technique::Swordfish => fish::basic(grid, 3),
+            Technique::HiddenTriple => subsets::hidden(grid, 3),
+            Technique::XYWing => wings::xy_wing(grid),
+            Technique::XYZWing => wings::xyz_wing(grid),
+            Technique::WWing => wings::w_wing(grid),
+            Technique::UniqueRectangle1 => uniqueness::type_1(grid),
+            Technique::UniqueRectangle2 => uniqueness::type_2_or_5(grid, self),
+            Technique::UniqueRectangle3 => uniqueness::type_3(grid),
+            Technique::UniqueRectangle4 => uniqueness::type_4(grid),
+            Technique::UniqueRectangle5 => uniqueness::type_2_or_5(grid, self),
+            Technique::UniqueRectangle6 => uniqueness::type_6(grid),
+            Technique::HiddenRectangle => uniqueness::hidden(grid),
+            Technique::BugPlusOne => uniqueness::bug_plus_one(grid),
+            Technique::FinnedXWing => fish::finned(grid, 2, false),
+            Technique::SashimiXWing => fish::finned(grid, 2, true),
+            Technique::NakedQuad => subsets::naked(grid, 4),
+            Technique::Jellyfish => fish::basic(grid, 4),
+            Technique::HiddenQuad => subsets::hidden(grid, 4),
+            Technique::FinnedSwordfish => fish::finned(grid, 3, false),
+            Technique::SashimiSwordfish => fish::finned(grid, 3, true),
+            Technique::FinnedJellyfish => fish::finned(grid, 4, false),
+            Technique::SashimiJellyfish => fish::finned(grid, 4, true),
+            Technique::XChain => chains::x_chain(grid),
+            Technique::XYChain => chains::xy_chain(grid),
+            Technique::AlternatingInferenceChain => chains::aic(grid),
+            Technique::GroupedAlternatingInferenceChain => chains::grouped_aic(grid),
+            Technique::AlsXz => als::xz(grid),
+            Technique::AlsXyWing => als::xy_wing(grid),
+            Technique::DeathBlossom => als::death_blossom(grid),
+        }
+    }
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Technique::HiddenSingle => "hidden single",
+            Technique::NakedSingle => "naked single",
+            Technique::LockedCandidatesPointing => "locked candidates (pointing)",
+            Technique::LockedCandidatesClaiming => "locked candidates (claiming)",
+            Technique::NakedPair => "naked pair",
+            Technique::XWing => "X-Wing",
+            Technique::HiddenPair => "hidden pair",
+            Technique::NakedTriple => "naked triple",
+            Technique::Swordfish => "swordfish",
+            Technique::HiddenTriple => "hidden triple",
+            Technique::XYWing => "XY-Wing",
+            Technique::XYZWing => "XYZ-Wing",
+            Technique::WWing => "W-Wing",
+            Technique::UniqueRectangle1 => "unique rectangle type 1",
+            Technique::UniqueRectangle2 => "unique rectangle type 2",
+            Technique::UniqueRectangle3 => "unique rectangle type 3",
+            Technique::UniqueRectangle4 => "unique rectangle type 4",
+            Technique::UniqueRectangle5 => "unique rectangle type 5",
+            Technique::UniqueRectangle6 => "unique rectangle type 6",
+            Technique::HiddenRectangle => "hidden rectangle",
+            Technique::BugPlusOne => "BUG+1",
+            Technique::FinnedXWing => "finned X-Wing",
+            Technique::SashimiXWing => "sashimi X-Wing",
+            Technique::NakedQuad => "naked quad",
+            Technique::Jellyfish => "jellyfish",
+            Technique::HiddenQuad => "hidden quad",
+            Technique::FinnedSwordfish => "finned swordfish",
+            Technique::SashimiSwordfish => "sashimi swordfish",
+            Technique::FinnedJellyfish => "finned jellyfish",
+            Technique::SashimiJellyfish => "sashimi jellyfish",
+            Technique::XChain => "X-Chain",
+            Technique::XYChain => "XY-Chain",
+            Technique::AlternatingInferenceChain => "AIC",
+            Technique::GroupedAlternatingInferenceChain => "grouped AIC",
+            Technique::AlsXz => "ALS-XZ",
+            Technique::AlsXyWing => "ALS-XY-Wing",
+            Technique::DeathBlossom => "death blossom",
+        };
+        f.write_str(name)
+    }
+}
+
+/// One deduction: the technique, what it concluded and the cells,
+/// houses and elements that make up the pattern it found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+
+    /// Elements placed, with the cell they go in.
+    pub placements: Vec<(Cell, ElementType)>,
+
+    /// Candidates removed, with the cell they are removed from.
+    pub eliminations: Vec<(Cell, ElementType)>,
+
+    /// Cells making up the pattern.
+    pub cells: Vec<Cell>,
+
+    /// Houses the pattern lies in. For fish, the base houses.
+    pub houses: Vec<House>,
+
+    /// For fish, the cover houses.
+    pub cover: Vec<House>,
+
+    /// For finned fish, the candidates outside the cover houses.
+    pub fins: Vec<Cell>,
+
+    /// For wings, the cell the pincers are joined through. For a Death
+    /// Blossom, the stem.
+    pub pivot: Option<Cell>,
+
+    /// For wings, the cells one of which must hold the eliminated
+    /// element.
+    pub pincers: Vec<Cell>,
+
+    /// Elements the pattern is about. For almost locked sets, the
+    /// restricted common candidates joining them; for a Death Blossom,
+    /// the candidates of the stem, one for each set.
+    pub elements: Vec<ElementType>,
+
+    /// Almost locked sets making up the pattern. For an ALS-XY-Wing the
+    /// middle one is joined to the other two.
+    pub sets: Vec<AlmostLockedSet>,
+
+    /// For chains, the chain in Eureka notation, like
+    /// "(1)r1c1=(1)r1c5-(1)r6c5=(1)r6c2".
+    pub chain: Option<String>,
+}
+
+impl Step {
+    /// A step with nothing concluded yet.
+    fn new(technique: Technique) -> Step {
+        Step {
+            technique,
+            placements: Vec::new(),
+            eliminations: Vec::new(),
+            cells: Vec::new(),
+            houses: Vec::new(),
+            cover: Vec::new(),
+            fins: Vec::new(),
+            pivot: None,
+            pincers: Vec::new(),
+            elements: Vec::new(),
+            sets: Vec::new(),
+            chain: None,
+        }
+    }
+
+    /// Makes the step's conclusions in `grid`.
+    fn apply(&self, grid: &mut CandidateGrid) {
+        let n = grid.elements();
+        for &(cell, value) in &self.placements {
+            grid.place(cell.index(n), value);
+        }
+        for &(cell, value) in &self.eliminations {
+            grid.eliminate(cell.index(n), value);
+        }
+    }
+}
+
+/// Writes placements like "r3c5 = 7 (hidden single in box 2)",
+/// eliminations like "naked pair: 1,5 in r1c2 r1c7 (row 1) => r1c3<>1"
+/// fish like "X-Wing: 5 in row 2, row 7 / column 3, column 8 => r4c3<>5"
+/// wings like "XY-Wing: 1,2,3 pivot r1c1 pincers r1c5 r3c2 => r3c5<>3"
+/// chains like "X-Chain: (1)r1c1=(1)r1c5-(1)r6c5=(1)r6c2 => r2c2<>1" and
+/// almost locked sets like "ALS-XZ: 1 in r1c2 r1c3 (row 1) / r5c1 (box 4)
+/// => r2c1<>5".
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.placements.is_empty() {
+            let placements: Vec<String> = self
+                .placements
+                .iter()
+                .map(|(cell, value)| format!("{} = {}", cell, format_element(*value)))
+                .collect();
+            write!(f, "{} ({}", placements.join(", "), self.technique)?;
+            if !self.houses.is_empty() {
+                write!(f, " in {}", join(&self.houses, ", "))?;
+            }
+            return write!(f, ")");
+        }
+        write!(f, "{}: ", self.technique)?;
+        if let Some(chain) = &self.chain {
+            write!(f, "{}", chain)?;
+        } else if !self.cover.is_empty() {
+            write!(f, "{}", join_elements(&self.elements))?;
+            write!(f, " in {} / {}", join(&self.houses, ", "), join(&self.cover, ", "))?;
+            if !self.fins.is_empty() {
+                write!(f, " with fins {}", join(&self.fins, " "))?;
+            }
+        } else if !self.sets.is_empty() {
+            write!(f, "{}", join_elements(&self.elements))?;
+            match self.pivot {
+                Some(stem) => write!(f, " stem {} petals", stem)?,
+                None => write!(f, " in")?,
+            }
+            write!(f, " {}", join(&self.sets, " / "))?;
+        } else if !self.pincers.is_empty() {
+            write!(f, "{}", join_elements(&self.elements))?;
+            if let Some(pivot) = self.pivot {
+                write!(f, " pivot {}", pivot)?;
+            }
+            write!(f, " pincers {}", join(&self.pincers, " "))?;
+            if !self.cells.is_empty() {
+                write!(f, " via {}", join(&self.cells, " "))?;
+            }
+            if !self.houses.is_empty() {
+                write!(f, " ({})", join(&self.houses, ", "))?;
+            }
+        } else {
+            write!(f, "{}", join_elements(&self.elements))?;
+            if !self.cells.is_empty() {
+                write!(f, " in {}", join(&self.cells, " "))?;
+            }
+            if !self.houses.is_empty() {
+                write!(f, " ({})", join(&self.houses, ", "))?;
+            }
+        }
+        let eliminations: Vec<String> = self
+            .eliminations
+            .iter()
+            .map(|(cell, value)| format!("{}<>{}", cell, format_element(*value)))
+            .collect();
+        write!(f, " => {}", eliminations.join(", "))
+    }
+}
+
+/// Joins displayable things with `separator` in between.
+fn join<T: fmt::Display>(items: &[T], separator: &str) -> String {
+    let strings: Vec<String> = items.iter().map(|x| x.to_string()).collect();
+    strings.join(separator)
+}
+
+/// Joins elements like "1,5".
+fn join_elements(elements: &[ElementType]) -> String {
+    let strings: Vec<String> = elements.iter().map(|&x| format_element(x)).collect();
+    strings.join(",")
+}
+
+/// Cells of a house with one more candidate between them than cells.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlmostLockedSet {
+    pub house: House,
+    pub cells: Vec<Cell>,
+    pub candidates: Vec<ElementType>,
+}
+
+/// Writes the set like "r1c2 r1c3 (row 1)".
+impl fmt::Display for AlmostLockedSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", join(&self.cells, " "), self.house)
+    }
+}
+
+/// The outcome of solving logically.
+pub struct LogicalSolution {
+    /// The steps taken, in order.
+    pub steps: Vec<Step>,
+
+    /// The grid after all steps; full unless the techniques ran out.
+    pub grid: SudokuGrid,
+}
+
+impl LogicalSolution {
+    /// True if the steps filled in every cell.
+    pub fn is_solved(&self) -> bool {
+        self.grid.cells.iter().all(|&x| x != 0)
+    }
+}
+
+/// Options that change which techniques the logical solver may use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogicOptions {
+    /// Also use techniques that assume the grid has exactly one
+    /// solution, like unique rectangles. Off by default, as on grids
+    /// with several solutions they remove candidates that are right.
+    /// Ignored for grids with cages.
+    pub assume_unique: bool,
+}
+
+/// Returns the easiest step available in `grid`, if any.
+fn find_step(grid: &CandidateGrid, options: LogicOptions) -> Option<Step> {
+    Technique::ALL
+        .iter()
+        // Cages can tell apart the solutions of a deadly pattern.
+        .filter(|technique| {
+            let unique = options.assume_unique && grid.grid().cages().is_empty();
+            unique || !technique.assumes_uniqueness()
+        })
+        .find_map(|technique| technique.find(grid))
+}
+
+/// Solves `grid` one step at a time using the easiest technique that
+/// applies, until the grid is full or no technique applies.
+pub fn solve(grid: &SudokuGrid) -> Result<LogicalSolution, SudokuError> {
+    solve_with_options(grid, LogicOptions::default())
+}
+
+/// Like `solve`, but lets the caller choose which techniques to use.
+pub fn solve_with_options(grid: &SudokuGrid, options: LogicOptions) -> Result<LogicalSolution, SudokuError> {
+    check(grid)?;
+    let mut candidates = CandidateGrid::new(grid);
+    let mut steps = Vec::new();
+    while !candidates.is_solved() {
+        match find_step(&candidates, options) {
+            Some(step) => {
+                step.apply(&mut candidates);
+                steps.push(step);
+            }
+            None => break,
+        }
+    }
+    Ok(LogicalSolution {
+        steps,
+        grid: candidates.grid().clone(),
+    })
+}
+
+/// Returns the easiest step to take next in `grid`, such as a grid a
+/// player has filled in part of, as a hint. `None` if the grid is full
+/// or no technique applies. Fails if the grid has no solution, as then
+/// some entry is wrong and hints would only mislead.
+pub fn hint(grid: &SudokuGrid) -> Result<Option<Step>, SudokuError> {
+    hint_with_options(grid, LogicOptions::default())
+}
+
+/// Like `hint`, but lets the caller choose which techniques to use.
+pub fn hint_with_options(grid: &SudokuGrid, options: LogicOptions) -> Result<Option<Step>, SudokuError> {
+    if count_solutions(grid, Some(1))? == 0 {
+        return Err(SudokuError::NoSolution);
+    }
+    Ok(find_step(&CandidateGrid::new(grid), options))
+}
+
+/// Iterates all ways to choose `k` of the numbers `0..n`, each as a
+/// sorted list, in lexicographic order.
+fn combinations(n: SizeType, k: SizeType) -> impl Iterator<Item = Vec<SizeType>> {
+    let mut next = if k <= n { Some((0..k).collect::<Vec<_>>()) } else { None };
+    std::iter::from_fn(move || {
+        let current = next.take()?;
+        // Find the rightmost position that can still be increased, and
+        // reset everything after it to follow on directly.
+        if let Some(i) = (0..k).rev().find(|&i| current[i] < n - k + i) {
+            let mut following = current.clone();
+            following[i] += 1;
+            for j in i + 1..k {
+                following[j] = following[j - 1] + 1;
+            }
+            next = Some(following);
+        }
+        Some(current)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{generate_puzzle, PuzzleOptions};
+    use crate::{element_bit, parse};
+
+    /// An empty 9x9 grid with the candidates of some cells, given as
+    /// (row, column, candidates) numbered from zero, cut down.
+    pub(super) fn grid_with(cells: &[(SizeType, SizeType, &[ElementType])]) -> CandidateGrid {
+        cut_down(&SudokuGrid::new(9).unwrap(), cells)
+    }
+
+    /// Like `grid_with`, but starting from the candidates of `grid`.
+    pub(super) fn cut_down(grid: &SudokuGrid, cells: &[(SizeType, SizeType, &[ElementType])]) -> CandidateGrid {
+        let n = grid.elements;
+        let mut candidates = CandidateGrid::new(grid);
+        for &(row, column, keep) in cells {
+            let keep = keep.iter().fold(0, |m, &v| m | element_bit(v));
+            for value in (1..=n as ElementType).filter(|&v| keep & element_bit(v) == 0) {
+                candidates.eliminate(row * n + column, value);
+            }
+        }
+        candidates
+    }
+
+    /// An empty 9x9 grid where 1 only fits in the given columns of some
+    /// rows, given as (row, columns) numbered from zero.
+    pub(super) fn ones_in_rows(rows: &[(SizeType, &[SizeType])]) -> CandidateGrid {
+        let mut cells: Vec<(SizeType, SizeType, &[ElementType])> = Vec::new();
+        for &(row, columns) in rows {
+            for column in (0..9).filter(|c| !columns.contains(c)) {
+                cells.push((row, column, &[2, 3, 4, 5, 6, 7, 8, 9]));
+            }
+        }
+        grid_with(&cells)
+    }
+
+    #[test]
+    fn given_5_and_2_combinations_should_return_all_10_pairs_in_order() {
+        let pairs: Vec<Vec<SizeType>> = combinations(5, 2).collect();
+        assert_eq!(10, pairs.len());
+        assert_eq!(vec![0, 1], pairs[0]);
+        assert_eq!(vec![0, 2], pairs[1]);
+        assert_eq!(vec![3, 4], pairs[9]);
+    }
+
+    #[test]
+    fn given_k_larger_than_n_combinations_should_return_nothing() {
+        assert_eq!(0, combinations(2, 3).count());
+        assert_eq!(1, combinations(3, 0).count());
+    }
+
+    #[test]
+    fn given_easy_sample_solve_should_solve_with_singles_only() {
+        let grid = parse(include_str!("../../samples/easy.sudoku")).unwrap();
+        let solution = solve(&grid).unwrap();
+        assert!(solution.is_solved());
+        let expected = crate::solutions(&grid).unwrap().next().unwrap();
+        assert_eq!(expected.cells, solution.grid.cells);
+        assert!(solution.steps.iter().all(|step| matches!(
+            step.technique,
+            Technique::HiddenSingle | Technique::NakedSingle
+        )));
+    }
+
+    #[test]
+    fn given_samples_solve_should_only_place_correct_elements() {
+        for input in [
+            include_str!("../../samples/kind-of-hard.sudoku"),
+            include_str!("../../samples/impossible.sudoku"),
+            include_str!("../../samples/very-hard.sudoku"),
+            include_str!("../../samples/hard-16x16.sudoku"),
+        ] {
+            let grid = parse(input).unwrap();
+            let expected = crate::solutions(&grid).unwrap().next().unwrap();
+            let solution = solve(&grid).unwrap();
+            let n = grid.elements;
+            for step in &solution.steps {
+                for &(cell, value) in &step.placements {
+                    assert_eq!(expected.cells[cell.index(n)], value, "{}", step);
+                }
+                for &(cell, value) in &step.eliminations {
+                    assert_ne!(expected.cells[cell.index(n)], value, "{}", step);
+                }
+            }
+        }
+    }
+
+    /// Solves generated puzzles, checking every step against the solution,
+    /// and returns the techniques used.
+    fn techniques_solving(seeds: &[u64], options: LogicOptions) -> Vec<Technique> {
+        let mut techniques = Vec::new();
+        for &seed in seeds {
+            let puzzle_options = PuzzleOptions { seed, ..Default::default() };
+            let grid = generate_puzzle(&puzzle_options).unwrap();
+            let expected = crate::solutions(&grid).unwrap().next().unwrap();
+            let solution = solve_with_options(&grid, options).unwrap();
+            for step in &solution.steps {
+                for &(cell, value) in &step.placements {
+                    assert_eq!(expected.cells[cell.index(9)], value, "{}", step);
+                }
+                for &(cell, value) in &step.eliminations {
+                    assert_ne!(expected.cells[cell.index(9)], value, "{}", step);
+                }
+                techniques.push(step.technique);
+            }
+        }
+        techniques
+    }
+
+    #[test]
+    fn given_all_techniques_should_be_tried_from_lowest_score() {
+        for pair in Technique::ALL.windows(2) {
+            assert!(pair[0].score() <= pair[1].score(), "{} before {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn given_w_wing_and_unique_rectangle_find_step_should_take_lower_scored_w_wing() {
+        // A unique rectangle on 3 and 4 in r1c1 r1c4 r2c1 r2c4, with 3 and 4
+        // gone from the houses of the corners so no pair or locked candidates
+        // come first, and a W-Wing on 1 and 2 elsewhere.
+        let mut cells: Vec<(SizeType, SizeType, &[ElementType])> =
+            vec![(0, 0, &[3, 4, 5]), (0, 3, &[3, 4]), (1, 0, &[3, 4]), (1, 3, &[3, 4]), (4, 4, &[1, 2]), (6, 7, &[1, 2])];
+        let without_3_4: &[ElementType] = &[1, 2, 5, 6, 7, 8, 9];
+        for column in [1, 2, 4, 5, 6, 7, 8] {
+            cells.push((1, column, without_3_4));
+        }
+        for row in 2..9 {
+            cells.push((row, 3, without_3_4));
+        }
+        for (row, column) in [(0, 4), (0, 5), (2, 4), (2, 5)] {
+            cells.push((row, column, without_3_4));
+        }
+        for column in [0, 1, 2, 3, 5, 6, 8] {
+            cells.push((8, column, &[2, 3, 4, 5, 6, 7, 8, 9]));
+        }
+        let grid = grid_with(&cells);
+        assert!(uniqueness::type_1(&grid).is_some());
+        let step = find_step(&grid, LogicOptions { assume_unique: true }).unwrap();
+        assert_eq!(Technique::WWing, step.technique);
+    }
+
+    #[test]
+    fn given_unique_puzzles_solve_assuming_uniqueness_should_only_make_correct_deductions() {
+        let techniques = techniques_solving(&[1, 6, 19, 27], LogicOptions { assume_unique: true });
+        assert!(techniques.contains(&Technique::UniqueRectangle1));
+        assert!(techniques.contains(&Technique::UniqueRectangle6));
+        assert!(techniques.contains(&Technique::BugPlusOne));
+    }
+
+    #[test]
+    fn given_puzzles_needing_almost_locked_sets_solve_should_only_make_correct_deductions() {
+        let techniques = techniques_solving(&[210, 728], LogicOptions::default());
+        assert!(techniques.contains(&Technique::AlsXz));
+        assert!(techniques.contains(&Technique::AlsXyWing));
+        assert!(techniques.contains(&Technique::DeathBlossom));
+    }
+
+    #[test]
+    fn given_default_options_solve_should_not_assume_uniqueness() {
+        let grid = parse(include_str!("../../samples/with-many-solutions.sudoku")).unwrap();
+        let solution = solve(&grid).unwrap();
+        assert!(solution.steps.iter().all(|step| !step.technique.assumes_uniqueness()));
+        let options = PuzzleOptions { seed: 6, ..Default::default() };
+        let grid = generate_puzzle(&options).unwrap();
+        let solution = solve(&grid).unwrap();
+        assert!(solution.steps.iter().all(|step| !step.technique.assumes_uniqueness()));
+    }
+
+    #[test]
+    fn given_partly_filled_grid_hint_should_return_easiest_step() {
+        let mut grid = parse(include_str!("../../samples/easy.sudoku")).unwrap();
+        let solution = crate::solutions(&grid).unwrap().next().unwrap();
+        // The player has filled in the first few empty cells.
+        let empty: Vec<SizeType> = (0..grid.size).filter(|&i| grid.cells[i] == 0).collect();
+        for &i in &empty[..10] {
+            grid.cells[i] = solution.cells[i];
+        }
+        let step = hint(&grid).unwrap().unwrap();
+        assert_eq!(Technique::HiddenSingle, step.technique);
+        let (cell, value) = step.placements[0];
+        assert_eq!(0, grid.cells[cell.index(9)]);
+        assert_eq!(solution.cells[cell.index(9)], value);
+        assert_eq!(vec![cell], step.cells);
+        assert_eq!(1, step.houses.len());
+    }
+
+    #[test]
+    fn given_stuck_grid_hint_should_return_elimination() {
+        let grid = parse(include_str!("../../samples/very-hard.sudoku")).unwrap();
+        let mut candidates = CandidateGrid::new(&grid);
+        let step = loop {
+            let step = find_step(&candidates, LogicOptions::default()).unwrap();
+            if step.placements.is_empty() {
+                break step;
+            }
+            step.apply(&mut candidates);
+        };
+        // Only placements were made, so the grid alone gives the same step.
+        assert_eq!(Some(step), hint(candidates.grid()).unwrap());
+    }
+
+    #[test]
+    fn given_full_grid_hint_should_return_none() {
+        let grid = crate::solutions(&SudokuGrid::new(4).unwrap()).unwrap().next().unwrap();
+        assert_eq!(None, hint(&grid).unwrap());
+    }
+
+    #[test]
+    fn given_wrong_entry_hint_should_fail() {
+        let mut grid = parse(include_str!("../../samples/easy.sudoku")).unwrap();
+        let solution = crate::solutions(&grid).unwrap().next().unwrap();
+        let i = (0..grid.size).find(|&i| grid.cells[i] == 0).unwrap();
+        // Any other candidate of the cell is wrong.
+        let wrong = crate::mask_elements(grid.candidates(i)).find(|&v| v != solution.cells[i]);
+        grid.cells[i] = wrong.unwrap();
+        assert!(hint(&grid).is_err());
+    }
+
+    #[test]
+    fn given_hidden_single_step_display_should_explain_it() {
+        let step = Step {
+            placements: vec![(Cell { row: 2, column: 4 }, 7)],
+            houses: vec![House::Box(1)],
+            ..Step::new(Technique::HiddenSingle)
+        };
+        assert_eq!("r3c5 = 7 (hidden single in box 2)", step.to_string());
+    }
+
+    #[test]
+    fn given_naked_pair_step_display_should_explain_it() {
+        let step = Step {
+            eliminations: vec![(Cell { row: 0, column: 2 }, 1)],
+            cells: vec![Cell { row: 0, column: 1 }, Cell { row: 0, column: 6 }],
+            houses: vec![House::Row(0)],
+            elements: vec![1, 5],
+            ..Step::new(Technique::NakedPair)
+        };
+        assert_eq!("naked pair: 1,5 in r1c2 r1c7 (row 1) => r1c3<>1", step.to_string());
+    }
+}
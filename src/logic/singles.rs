@@ -0,0 +1,68 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Placing elements: naked and hidden singles.
+
+use super::{Cell, Step, Technique};
+use crate::candidates::CandidateGrid;
+use crate::{highest_element, ElementType, SizeType};
+
+/// Finds an empty cell with only one candidate.
+pub(super) fn naked_single(grid: &CandidateGrid) -> Option<Step> {
+    let n = grid.elements();
+    let index = (0..grid.size()).find(|&i| grid.candidates(i).count_ones() == 1)?;
+    let value = highest_element(grid.candidates(index));
+    let cell = Cell::of(index, n);
+    Some(Step {
+        placements: vec![(cell, value)],
+        cells: vec![cell],
+        elements: vec![value],
+        ..Step::new(Technique::NakedSingle)
+    })
+}
+
+/// Finds an element that has only one possible cell in a house.
+pub(super) fn hidden_single(grid: &CandidateGrid) -> Option<Step> {
+    let n = grid.elements();
+    // Look in boxes first, as a person would; they're easier to scan.
+    let mut order: Vec<SizeType> = (0..grid.houses().len()).collect();
+    order.sort_by_key(|&h| grid.labels()[h].is_line());
+    for h in order {
+        for value in 1..=n as ElementType {
+            let places = grid.places(h, value);
+            if places.len() != 1 {
+                continue;
+            }
+            let cell = Cell::of(places[0], n);
+            return Some(Step {
+                placements: vec![(cell, value)],
+                cells: vec![cell],
+                houses: vec![grid.labels()[h]],
+                elements: vec![value],
+                ..Step::new(Technique::HiddenSingle)
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn given_cell_with_one_candidate_naked_single_should_place_it() {
+        // r1c4 sees 1, 2 and 3.
+        let grid = parse("12.. ..3. .... ....").unwrap();
+        let step = naked_single(&CandidateGrid::new(&grid)).unwrap();
+        assert_eq!(step.to_string(), "r1c3 = 4 (naked single)");
+    }
+
+    #[test]
+    fn given_element_with_one_place_in_box_hidden_single_should_place_it() {
+        let grid = parse("1... .... ..1. ....").unwrap();
+        let step = hidden_single(&CandidateGrid::new(&grid)).unwrap();
+        assert_eq!(step.to_string(), "r2c4 = 1 (hidden single in box 2)");
+    }
+}
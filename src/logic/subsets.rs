@@ -0,0 +1,157 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Naked and hidden subsets: pairs, triples and quads.
+
+use super::{combinations, Cell, Step, Technique};
+use crate::candidates::CandidateGrid;
+use crate::{element_bit, mask_elements, ElementType, MaskType, SizeType};
+
+/// Finds `k` cells in a house with only `k` candidates between them,
+/// which then can't be anywhere else in the house.
+pub(super) fn naked(grid: &CandidateGrid, k: SizeType) -> Option<Step> {
+    let n = grid.elements();
+    for (h, house) in grid.houses().iter().enumerate() {
+        let cells: Vec<SizeType> = house
+            .iter()
+            .copied()
+            .filter(|&i| (2..=k as u32).contains(&grid.candidates(i).count_ones()))
+            .collect();
+        for chosen in combinations(cells.len(), k) {
+            let chosen: Vec<SizeType> = chosen.iter().map(|&j| cells[j]).collect();
+            let union = chosen.iter().fold(0, |m, &i| m | grid.candidates(i));
+            if union.count_ones() as SizeType != k {
+                continue;
+            }
+            let mut eliminations = Vec::new();
+            for &i in house.iter().filter(|i| !chosen.contains(i)) {
+                for value in mask_elements(grid.candidates(i) & union) {
+                    eliminations.push((Cell::of(i, n), value));
+                }
+            }
+            if eliminations.is_empty() {
+                continue;
+            }
+            return Some(Step {
+                eliminations,
+                cells: chosen.iter().map(|&i| Cell::of(i, n)).collect(),
+                houses: vec![grid.labels()[h]],
+                elements: mask_elements(union).collect(),
+                ..Step::new(naked_technique(k))
+            });
+        }
+    }
+    None
+}
+
+/// Finds `k` elements that only fit in the same `k` cells of a house,
+/// which then can't hold any other element.
+pub(super) fn hidden(grid: &CandidateGrid, k: SizeType) -> Option<Step> {
+    let n = grid.elements();
+    for h in 0..grid.houses().len() {
+        let values: Vec<ElementType> = (1..=n as ElementType)
+            .filter(|&v| (2..=k).contains(&grid.places(h, v).len()))
+            .collect();
+        for chosen in combinations(values.len(), k) {
+            let chosen: Vec<ElementType> = chosen.iter().map(|&j| values[j]).collect();
+            let mut cells: Vec<SizeType> = chosen.iter().flat_map(|&v| grid.places(h, v)).collect();
+            cells.sort_unstable();
+            cells.dedup();
+            if cells.len() != k {
+                continue;
+            }
+            let mask: MaskType = chosen.iter().fold(0, |m, &v| m | element_bit(v));
+            let mut eliminations = Vec::new();
+            for &i in &cells {
+                for value in mask_elements(grid.candidates(i) & !mask) {
+                    eliminations.push((Cell::of(i, n), value));
+                }
+            }
+            if eliminations.is_empty() {
+                continue;
+            }
+            return Some(Step {
+                eliminations,
+                cells: cells.iter().map(|&i| Cell::of(i, n)).collect(),
+                houses: vec![grid.labels()[h]],
+                elements: chosen,
+                ..Step::new(hidden_technique(k))
+            });
+        }
+    }
+    None
+}
+
+fn naked_technique(k: SizeType) -> Technique {
+    match k {
+        2 => Technique::NakedPair,
+        3 => Technique::NakedTriple,
+        _ => Technique::NakedQuad,
+    }
+}
+
+fn hidden_technique(k: SizeType) -> Technique {
+    match k {
+        2 => Technique::HiddenPair,
+        3 => Technique::HiddenTriple,
+        _ => Technique::HiddenQuad,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn given_two_cells_with_same_two_candidates_naked_pair_should_clear_row() {
+        // r1c1 and r1c2 can only be 1 or 2.
+        let grid = parse(
+            "
+            ... ... ...
+            ... ... ...
+            345 ... ...
+
+            69. ... ...
+            7.. ... ...
+            8.. ... ...
+
+            96. ... ...
+            .7. ... ...
+            .8. ... ...
+            ",
+        )
+        .unwrap();
+        let step = naked(&CandidateGrid::new(&grid), 2).unwrap();
+        assert_eq!(step.technique, Technique::NakedPair);
+        assert_eq!(step.elements, vec![1, 2]);
+        assert_eq!(step.cells, vec![Cell { row: 0, column: 0 }, Cell { row: 0, column: 1 }]);
+        assert!(step.eliminations.contains(&(Cell { row: 0, column: 2 }, 1)));
+    }
+
+    #[test]
+    fn given_two_elements_in_same_two_cells_hidden_pair_should_clear_cells() {
+        // In row 1, the 1 and 2 only fit in r1c1 and r1c2.
+        let grid = parse(
+            "
+            ... ... ...
+            ... 12. ...
+            ... ... 12.
+
+            ..1 ... ...
+            ..2 ... ...
+            ... ... ...
+
+            ... ... ...
+            ... ... ...
+            ... ... ...
+            ",
+        )
+        .unwrap();
+        let step = hidden(&CandidateGrid::new(&grid), 2).unwrap();
+        assert_eq!(step.technique, Technique::HiddenPair);
+        assert_eq!(step.elements, vec![1, 2]);
+        assert_eq!(step.cells, vec![Cell { row: 0, column: 0 }, Cell { row: 0, column: 1 }]);
+        assert!(step.eliminations.contains(&(Cell { row: 0, column: 0 }, 3)));
+    }
+}
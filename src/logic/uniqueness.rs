@@ -0,0 +1,469 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Deductions that assume the puzzle has exactly one solution: Unique
+//! Rectangles, Hidden Rectangles and BUG+1.
+//!
+//! Four cells at the corners of a rectangle spanning two rows, two
+//! columns and two boxes that could all only hold the same two elements
+//! form a deadly pattern: the elements could be swapped, giving two
+//! solutions. So in a unique puzzle some corner must hold something
+//! else. On puzzles with several solutions these deductions are wrong.
+
+use super::{combinations, Cell, Step, Technique};
+use crate::candidates::CandidateGrid;
+use crate::{element_bit, mask_elements, ElementType, House, MaskType, SizeType};
+
+/// Four unsolved cells at the corners of a rectangle spanning two boxes,
+/// all with both elements of `pair` as candidates.
+struct Rectangle {
+    // Top left, top right, bottom left, bottom right.
+    corners: [SizeType; 4],
+    pair: MaskType,
+}
+
+impl Rectangle {
+    /// Corners with no other candidates than the pair.
+    fn floor(&self, grid: &CandidateGrid) -> Vec<SizeType> {
+        self.corners
+            .iter()
+            .copied()
+            .filter(|&i| grid.candidates(i) == self.pair)
+            .collect()
+    }
+
+    /// Corners with other candidates too.
+    fn roof(&self, grid: &CandidateGrid) -> Vec<SizeType> {
+        self.corners
+            .iter()
+            .copied()
+            .filter(|&i| grid.candidates(i) != self.pair)
+            .collect()
+    }
+
+    /// The corner diagonally opposite `corner`.
+    fn opposite(&self, corner: SizeType) -> SizeType {
+        let k = self.corners.iter().position(|&i| i == corner).unwrap();
+        self.corners[3 - k]
+    }
+
+    /// The step for the rectangle with `eliminations`.
+    fn step(&self, grid: &CandidateGrid, technique: Technique, eliminations: Vec<(Cell, ElementType)>) -> Step {
+        let n = grid.elements();
+        Step {
+            eliminations,
+            cells: self.corners.iter().map(|&i| Cell::of(i, n)).collect(),
+            elements: mask_elements(self.pair).collect(),
+            ..Step::new(technique)
+        }
+    }
+}
+
+/// Every rectangle in `grid`, once for each pair its corners share.
+fn rectangles(grid: &CandidateGrid) -> Vec<Rectangle> {
+    let n = grid.elements();
+    let mut rectangles = Vec::new();
+    for r1 in 0..n {
+        for r2 in r1 + 1..n {
+            for c1 in 0..n {
+                for c2 in c1 + 1..n {
+                    let corners = [r1 * n + c1, r1 * n + c2, r2 * n + c1, r2 * n + c2];
+                    let boxes: Vec<SizeType> = corners.iter().map(|&i| grid.box_of(i)).collect();
+                    // Two boxes, each with both corners of a row or of a
+                    // column. Irregular regions can split them otherwise.
+                    let by_rows = boxes[0] == boxes[1] && boxes[2] == boxes[3];
+                    let by_columns = boxes[0] == boxes[2] && boxes[1] == boxes[3];
+                    if boxes[0] == boxes[3] || !(by_rows || by_columns) || !fits_extra_houses(grid, &corners) {
+                        continue;
+                    }
+                    let common = corners.iter().fold(MaskType::MAX, |m, &i| m & grid.candidates(i));
+                    let values: Vec<ElementType> = mask_elements(common).collect();
+                    for chosen in combinations(values.len(), 2) {
+                        let pair = element_bit(values[chosen[0]]) | element_bit(values[chosen[1]]);
+                        rectangles.push(Rectangle { corners, pair });
+                    }
+                }
+            }
+        }
+    }
+    rectangles
+}
+
+/// True if swapping the elements of a rectangle at `corners` would keep
+/// the houses other than rows, columns and boxes valid, as when each of
+/// them has none of the corners or both of a row or column. Extra
+/// houses like the diagonals of Sudoku-X often break deadly patterns.
+fn fits_extra_houses(grid: &CandidateGrid, corners: &[SizeType; 4]) -> bool {
+    grid.houses().iter().zip(grid.labels()).all(|(house, label)| {
+        if matches!(label, House::Row(_) | House::Column(_) | House::Box(_)) {
+            return true;
+        }
+        let inside: Vec<SizeType> = (0..4).filter(|&k| house.contains(&corners[k])).collect();
+        matches!(inside[..], [] | [0, 1] | [2, 3] | [0, 2] | [1, 3])
+    })
+}
+
+/// The houses containing every cell in `cells`.
+fn shared_houses(grid: &CandidateGrid, cells: &[SizeType]) -> Vec<SizeType> {
+    (0..grid.houses().len())
+        .filter(|&h| cells.iter().all(|i| grid.houses()[h].contains(i)))
+        .collect()
+}
+
+/// Removals of the elements in `values` from `cells`, where candidates.
+fn remove(grid: &CandidateGrid, cells: &[SizeType], values: MaskType) -> Vec<(Cell, ElementType)> {
+    let n = grid.elements();
+    let mut eliminations = Vec::new();
+    for &i in cells {
+        for value in mask_elements(grid.candidates(i) & values) {
+            eliminations.push((Cell::of(i, n), value));
+        }
+    }
+    eliminations
+}
+
+/// Finds a rectangle where three corners only have the pair, so the
+/// fourth can't be either of them.
+pub(super) fn type_1(grid: &CandidateGrid) -> Option<Step> {
+    rectangles(grid).iter().find_map(|rectangle| {
+        let roof = rectangle.roof(grid);
+        if roof.len() != 1 {
+            return None;
+        }
+        let eliminations = remove(grid, &roof, rectangle.pair);
+        Some(rectangle.step(grid, Technique::UniqueRectangle1, eliminations))
+    })
+}
+
+/// Finds a rectangle where the corners with more than the pair all have
+/// the same one extra candidate, which then must be in one of them and
+/// can't be in any cell seeing them all. Type 2 has two such corners in
+/// a row or column, type 5 two diagonal ones or three.
+pub(super) fn type_2_or_5(grid: &CandidateGrid, technique: Technique) -> Option<Step> {
+    let n = grid.elements();
+    rectangles(grid).iter().find_map(|rectangle| {
+        let roof = rectangle.roof(grid);
+        let extra = roof.first().map_or(0, |&i| grid.candidates(i) & !rectangle.pair);
+        if roof.len() == 4 || extra.count_ones() != 1 {
+            return None;
+        }
+        if roof.iter().any(|&i| grid.candidates(i) != rectangle.pair | extra) {
+            return None;
+        }
+        let in_line = roof.len() == 2 && (roof[0] / n == roof[1] / n || roof[0] % n == roof[1] % n);
+        let kind = if in_line { Technique::UniqueRectangle2 } else { Technique::UniqueRectangle5 };
+        if kind != technique {
+            return None;
+        }
+        let seeing: Vec<SizeType> = (0..grid.size())
+            .filter(|&i| roof.iter().all(|&r| grid.sees(i, r)))
+            .collect();
+        let eliminations = remove(grid, &seeing, extra);
+        (!eliminations.is_empty()).then(|| rectangle.step(grid, technique, eliminations))
+    })
+}
+
+/// Finds a rectangle with two corners in a line holding only the pair.
+/// One of the other two must hold one of their extra candidates, so they
+/// act as one cell with those candidates. Together with other cells of a
+/// house they share, they can form a naked subset.
+pub(super) fn type_3(grid: &CandidateGrid) -> Option<Step> {
+    let n = grid.elements();
+    for rectangle in rectangles(grid) {
+        let roof = rectangle.roof(grid);
+        if roof.len() != 2 {
+            continue;
+        }
+        let extras = (grid.candidates(roof[0]) | grid.candidates(roof[1])) & !rectangle.pair;
+        for h in shared_houses(grid, &roof) {
+            let others: Vec<SizeType> = grid.houses()[h]
+                .iter()
+                .copied()
+                .filter(|i| grid.candidates(*i) != 0 && !roof.contains(i))
+                .collect();
+            // Up to naked quads.
+            for k in 1..others.len().min(4) {
+                for chosen in combinations(others.len(), k) {
+                    let chosen: Vec<SizeType> = chosen.iter().map(|&j| others[j]).collect();
+                    let union = chosen.iter().fold(extras, |m, &i| m | grid.candidates(i));
+                    if union.count_ones() as SizeType != k + 1 {
+                        continue;
+                    }
+                    let rest: Vec<SizeType> = others.iter().copied().filter(|i| !chosen.contains(i)).collect();
+                    let eliminations = remove(grid, &rest, union);
+                    if eliminations.is_empty() {
+                        continue;
+                    }
+                    let mut step = rectangle.step(grid, Technique::UniqueRectangle3, eliminations);
+                    step.cells.extend(chosen.iter().map(|&i| Cell::of(i, n)));
+                    step.houses = vec![grid.labels()[h]];
+                    return Some(step);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Finds a rectangle with two corners holding only the pair, where one
+/// element of the pair only fits in the other two corners within a house
+/// they share. That element is in one of them, so the other element of
+/// the pair can't be in either.
+pub(super) fn type_4(grid: &CandidateGrid) -> Option<Step> {
+    for rectangle in rectangles(grid) {
+        let roof = rectangle.roof(grid);
+        if roof.len() != 2 {
+            continue;
+        }
+        for h in shared_houses(grid, &roof) {
+            for value in mask_elements(rectangle.pair) {
+                if grid.places(h, value) != roof {
+                    continue;
+                }
+                let eliminations = remove(grid, &roof, rectangle.pair & !element_bit(value));
+                let mut step = rectangle.step(grid, Technique::UniqueRectangle4, eliminations);
+                step.houses = vec![grid.labels()[h]];
+                return Some(step);
+            }
+        }
+    }
+    None
+}
+
+/// Finds a rectangle with two diagonal corners holding only the pair,
+/// where one element of the pair only fits in the corners within both
+/// rows, or within both columns. That element must then go in the two
+/// corners with just the pair, so it can't be in the other two.
+pub(super) fn type_6(grid: &CandidateGrid) -> Option<Step> {
+    let n = grid.elements();
+    for rectangle in rectangles(grid) {
+        let floor = rectangle.floor(grid);
+        if floor.len() != 2 || rectangle.opposite(floor[0]) != floor[1] {
+            continue;
+        }
+        let [top_left, top_right, bottom_left, _] = rectangle.corners;
+        let rows = [top_left / n, bottom_left / n];
+        let columns = [top_left % n, top_right % n];
+        for value in mask_elements(rectangle.pair) {
+            let fits = |cells: Vec<SizeType>| cells.iter().all(|i| rectangle.corners.contains(i));
+            let in_rows = rows.iter().all(|&r| fits(grid.places(r, value)));
+            let in_columns = columns.iter().all(|&c| fits(grid.places(n + c, value)));
+            if !in_rows && !in_columns {
+                continue;
+            }
+            let eliminations = remove(grid, &rectangle.roof(grid), element_bit(value));
+            return Some(rectangle.step(grid, Technique::UniqueRectangle6, eliminations));
+        }
+    }
+    None
+}
+
+/// Finds a rectangle with a corner holding only the pair, where one
+/// element of the pair only fits in the corners within the row and the
+/// column of the opposite corner. Were the opposite corner the other
+/// element, the four would form the deadly pattern.
+pub(super) fn hidden(grid: &CandidateGrid) -> Option<Step> {
+    let n = grid.elements();
+    for rectangle in rectangles(grid) {
+        for floor in rectangle.floor(grid) {
+            let opposite = rectangle.opposite(floor);
+            if grid.candidates(opposite) == rectangle.pair {
+                continue;
+            }
+            for value in mask_elements(rectangle.pair) {
+                let row = grid.places(opposite / n, value);
+                let column = grid.places(n + opposite % n, value);
+                let fits = |cells: &[SizeType]| cells.len() == 2 && cells.iter().all(|i| rectangle.corners.contains(i));
+                if !fits(&row) || !fits(&column) {
+                    continue;
+                }
+                let eliminations = remove(grid, &[opposite], rectangle.pair & !element_bit(value));
+                if !eliminations.is_empty() {
+                    return Some(rectangle.step(grid, Technique::HiddenRectangle, eliminations));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Finds a grid where all unsolved cells have two candidates but one,
+/// which has three, and every element fits in every house in exactly two
+/// places or none, except for one element in the houses of that cell.
+/// Without that element the cell would leave the grid with two solutions,
+/// so it goes there.
+pub(super) fn bug_plus_one(grid: &CandidateGrid) -> Option<Step> {
+    let n = grid.elements();
+    let mut trivalue = None;
+    for i in (0..grid.size()).filter(|&i| grid.candidates(i) != 0) {
+        match grid.candidates(i).count_ones() {
+            2 => {}
+            3 if trivalue.is_none() => trivalue = Some(i),
+            _ => return None,
+        }
+    }
+    let cell = trivalue?;
+    let row = cell / n;
+    let value = mask_elements(grid.candidates(cell)).find(|&v| grid.places(row, v).len() == 3)?;
+    let houses = shared_houses(grid, &[cell]);
+    for h in 0..grid.houses().len() {
+        for v in 1..=n as ElementType {
+            let expected = if v == value && houses.contains(&h) { 3 } else { 2 };
+            let count = grid.places(h, v).len();
+            if count != 0 && count != expected {
+                return None;
+            }
+        }
+    }
+    Some(Step {
+        placements: vec![(Cell::of(cell, n), value)],
+        ..Step::new(Technique::BugPlusOne)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::tests::{cut_down, grid_with};
+    use crate::variants::ExtraHouses;
+    use crate::SudokuGrid;
+
+    /// Like `grid_with`, for a grid with the `extra` houses.
+    fn grid_with_extra(cells: &[(SizeType, SizeType, &[ElementType])], extra: &[ExtraHouses]) -> CandidateGrid {
+        let mut sudoku = SudokuGrid::new(9).unwrap();
+        for &houses in extra {
+            sudoku.add_extra_houses(houses).unwrap();
+        }
+        cut_down(&sudoku, cells)
+    }
+
+    #[test]
+    fn given_three_corners_with_only_the_pair_type_1_should_clear_pair_from_fourth() {
+        let grid = grid_with(&[(0, 0, &[1, 2, 3]), (0, 3, &[1, 2]), (1, 0, &[1, 2]), (1, 3, &[1, 2])]);
+        let step = type_1(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "unique rectangle type 1: 1,2 in r1c1 r1c4 r2c1 r2c4 => r1c1<>1, r1c1<>2"
+        );
+    }
+
+    #[test]
+    fn given_one_corner_on_a_diagonal_type_1_should_find_no_deadly_pattern() {
+        let cells: &[(SizeType, SizeType, &[ElementType])] =
+            &[(0, 0, &[1, 2, 3]), (0, 3, &[1, 2]), (1, 0, &[1, 2]), (1, 3, &[1, 2])];
+        // Swapping 1 and 2 in the corners would change what r1c1 holds
+        // on the diagonal, where the other corners aren't.
+        assert!(type_1(&grid_with_extra(cells, &[ExtraHouses::Diagonals])).is_none());
+        // Disjoint groups hold the corners by rows, like the boxes.
+        assert!(type_1(&grid_with_extra(cells, &[ExtraHouses::DisjointGroups])).is_some());
+    }
+
+    #[test]
+    fn given_two_corners_with_same_extra_type_2_should_clear_extra_seeing_both() {
+        let grid = grid_with(&[(0, 0, &[1, 2]), (0, 3, &[1, 2]), (1, 0, &[1, 2, 5]), (1, 3, &[1, 2, 5])]);
+        assert!(type_1(&grid).is_none());
+        let step = type_2_or_5(&grid, Technique::UniqueRectangle2).unwrap();
+        assert_eq!(7, step.eliminations.len());
+        assert!(step.eliminations.iter().all(|&(cell, value)| cell.row == 1 && value == 5));
+        assert!(type_2_or_5(&grid, Technique::UniqueRectangle5).is_none());
+    }
+
+    #[test]
+    fn given_extra_corners_and_cell_forming_naked_pair_type_3_should_clear_rest_of_row() {
+        let grid = grid_with(&[(0, 0, &[1, 2]), (0, 3, &[1, 2]), (1, 0, &[1, 2, 3]), (1, 3, &[1, 2, 4]), (1, 6, &[3, 4])]);
+        let step = type_3(&grid).unwrap();
+        assert_eq!(step.houses, vec![House::Row(1)]);
+        assert_eq!(Cell { row: 1, column: 6 }, step.cells[4]);
+        // 3 and 4 leave the six other cells of row 2.
+        assert_eq!(12, step.eliminations.len());
+        assert!(step.eliminations.iter().all(|&(cell, value)| cell.row == 1 && (value == 3 || value == 4)));
+    }
+
+    #[test]
+    fn given_diagonal_corners_with_same_extra_type_5_should_clear_extra_seeing_both() {
+        let grid = grid_with(&[(0, 0, &[1, 2, 5]), (0, 3, &[1, 2]), (1, 0, &[1, 2]), (1, 3, &[1, 2, 5])]);
+        assert!(type_2_or_5(&grid, Technique::UniqueRectangle2).is_none());
+        let step = type_2_or_5(&grid, Technique::UniqueRectangle5).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "unique rectangle type 5: 1,2 in r1c1 r1c4 r2c1 r2c4 => r1c5<>5, r1c6<>5, r2c2<>5, r2c3<>5"
+        );
+    }
+
+    #[test]
+    fn given_pair_element_only_in_corners_of_both_rows_type_6_should_clear_it_from_extra_corners() {
+        let mut cells: Vec<(SizeType, SizeType, &[ElementType])> =
+            vec![(0, 0, &[1, 2]), (0, 3, &[1, 2, 5]), (1, 0, &[1, 2, 6]), (1, 3, &[1, 2])];
+        let no_one: &[ElementType] = &[2, 3, 4, 5, 6, 7, 8, 9];
+        for row in 0..2 {
+            for column in [1, 2, 4, 5, 6, 7, 8] {
+                cells.push((row, column, no_one));
+            }
+        }
+        let grid = grid_with(&cells);
+        let step = type_6(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "unique rectangle type 6: 1,2 in r1c1 r1c4 r2c1 r2c4 => r1c4<>1, r2c1<>1"
+        );
+    }
+
+    #[test]
+    fn given_bivalue_grid_but_one_cell_bug_plus_one_should_place_its_third_element() {
+        // A solved grid without its 1s, 2s and 3s. Those cells keep their
+        // own element and one more, in a pattern with each twice in every
+        // house, except the 3 at r1c3 which keeps all three.
+        let solved: Vec<ElementType> =
+            (0..81).map(|i| (((i / 9) % 3 * 3 + i / 27 + i % 9) % 9 + 1) as ElementType).collect();
+        let blanked: Vec<ElementType> = solved.iter().map(|&x| if x <= 3 { 0 } else { x }).collect();
+        let sudoku = SudokuGrid::load(&blanked).unwrap();
+        let cells: Vec<(SizeType, SizeType, &[ElementType])> = (0..81)
+            .filter(|&i| solved[i] <= 3 && i != 2)
+            .map(|i| {
+                let keep: &[ElementType] = match solved[i] {
+                    1 => &[1, 3],
+                    2 => &[2, 3],
+                    _ => &[1, 2],
+                };
+                (i / 9, i % 9, keep)
+            })
+            .collect();
+        let grid = cut_down(&sudoku, &cells);
+        assert_eq!(3, solved[2]);
+        let step = bug_plus_one(&grid).unwrap();
+        assert_eq!(step.to_string(), "r1c3 = 3 (BUG+1)");
+    }
+
+    #[test]
+    fn given_pair_element_locked_in_extra_corners_type_4_should_clear_the_other() {
+        let mut cells: Vec<(SizeType, SizeType, &[ElementType])> =
+            vec![(0, 0, &[1, 2]), (0, 3, &[1, 2]), (1, 0, &[1, 2, 5]), (1, 3, &[1, 2, 6])];
+        for column in [1, 2, 4, 5, 6, 7, 8] {
+            cells.push((1, column, &[2, 3, 4, 5, 6, 7, 8, 9]));
+        }
+        let grid = grid_with(&cells);
+        let step = type_4(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "unique rectangle type 4: 1,2 in r1c1 r1c4 r2c1 r2c4 (row 2) => r2c1<>2, r2c4<>2"
+        );
+    }
+
+    #[test]
+    fn given_strong_links_at_opposite_corner_hidden_should_clear_other_element() {
+        let mut cells: Vec<(SizeType, SizeType, &[ElementType])> = vec![(0, 0, &[1, 2]), (1, 3, &[1, 2, 7])];
+        let no_one: &[ElementType] = &[2, 3, 4, 5, 6, 7, 8, 9];
+        for column in [1, 2, 4, 5, 6, 7, 8] {
+            cells.push((1, column, no_one));
+        }
+        for row in 2..9 {
+            cells.push((row, 3, no_one));
+        }
+        let grid = grid_with(&cells);
+        let step = hidden(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "hidden rectangle: 1,2 in r1c1 r1c4 r2c1 r2c4 => r2c4<>2"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Wings: XY-Wing, XYZ-Wing and W-Wing.
+
+use super::{Cell, Step, Technique};
+use crate::candidates::CandidateGrid;
+use crate::{element_bit, highest_element, mask_elements, ElementType, MaskType, SizeType};
+
+/// Cells with exactly `count` candidates.
+fn cells_with(grid: &CandidateGrid, count: u32) -> Vec<SizeType> {
+    (0..grid.size())
+        .filter(|&i| grid.candidates(i).count_ones() == count)
+        .collect()
+}
+
+/// Eliminations of the single element in `z` from every cell seeing
+/// all of `seen`.
+fn eliminate_seeing(grid: &CandidateGrid, z: MaskType, seen: &[SizeType]) -> Vec<(Cell, ElementType)> {
+    let n = grid.elements();
+    let value = highest_element(z);
+    (0..grid.size())
+        .filter(|&i| grid.candidates(i) & z != 0 && seen.iter().all(|&s| grid.sees(i, s)))
+        .map(|i| (Cell::of(i, n), value))
+        .collect()
+}
+
+/// Finds a pivot xy seeing pincers xz and yz, and eliminates z from
+/// cells seeing both pincers.
+pub(super) fn xy_wing(grid: &CandidateGrid) -> Option<Step> {
+    let bivalue = cells_with(grid, 2);
+    for &pivot in &bivalue {
+        let xy = grid.candidates(pivot);
+        for &a in bivalue.iter().filter(|&&a| grid.sees(pivot, a)) {
+            let xz = grid.candidates(a);
+            if (xz & xy).count_ones() != 1 {
+                continue;
+            }
+            let z = xz & !xy;
+            let yz = (xy & !xz) | z;
+            for &b in bivalue.iter().filter(|&&b| b > a && grid.sees(pivot, b)) {
+                if grid.candidates(b) != yz {
+                    continue;
+                }
+                let eliminations = eliminate_seeing(grid, z, &[a, b]);
+                if !eliminations.is_empty() {
+                    return Some(wing(grid, Technique::XYWing, pivot, &[a, b], xy | z, eliminations));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Finds a pivot xyz seeing pincers xz and yz, and eliminates z from
+/// cells seeing the pivot and both pincers.
+pub(super) fn xyz_wing(grid: &CandidateGrid) -> Option<Step> {
+    let bivalue = cells_with(grid, 2);
+    for pivot in cells_with(grid, 3) {
+        let xyz = grid.candidates(pivot);
+        let pincers: Vec<SizeType> = bivalue
+            .iter()
+            .copied()
+            .filter(|&i| grid.sees(pivot, i) && grid.candidates(i) & !xyz == 0)
+            .collect();
+        for (j, &a) in pincers.iter().enumerate() {
+            for &b in &pincers[j + 1..] {
+                let (xz, yz) = (grid.candidates(a), grid.candidates(b));
+                let z = xz & yz;
+                if z.count_ones() != 1 || xz | yz != xyz {
+                    continue;
+                }
+                let eliminations = eliminate_seeing(grid, z, &[pivot, a, b]);
+                if !eliminations.is_empty() {
+                    return Some(wing(grid, Technique::XYZWing, pivot, &[a, b], xyz, eliminations));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Finds two cells xy that don't see each other, joined by a strong
+/// link on x, and eliminates y from cells seeing both.
+pub(super) fn w_wing(grid: &CandidateGrid) -> Option<Step> {
+    let n = grid.elements();
+    let bivalue = cells_with(grid, 2);
+    for (j, &a) in bivalue.iter().enumerate() {
+        let xy = grid.candidates(a);
+        for &b in &bivalue[j + 1..] {
+            if grid.candidates(b) != xy || grid.sees(a, b) {
+                continue;
+            }
+            for x in mask_elements(xy) {
+                let y = xy & !element_bit(x);
+                for h in 0..grid.houses().len() {
+                    let places = grid.places(h, x);
+                    if places.len() != 2 || places.contains(&a) || places.contains(&b) {
+                        continue;
+                    }
+                    let (p, q) = (places[0], places[1]);
+                    let linked = (grid.sees(p, a) && grid.sees(q, b))
+                        || (grid.sees(p, b) && grid.sees(q, a));
+                    if !linked {
+                        continue;
+                    }
+                    let eliminations = eliminate_seeing(grid, y, &[a, b]);
+                    if eliminations.is_empty() {
+                        continue;
+                    }
+                    return Some(Step {
+                        eliminations,
+                        cells: vec![Cell::of(p, n), Cell::of(q, n)],
+                        houses: vec![grid.labels()[h]],
+                        pincers: vec![Cell::of(a, n), Cell::of(b, n)],
+                        elements: vec![x, highest_element(y)],
+                        ..Step::new(Technique::WWing)
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The step for an XY- or XYZ-Wing.
+fn wing(
+    grid: &CandidateGrid,
+    technique: Technique,
+    pivot: SizeType,
+    pincers: &[SizeType],
+    elements: MaskType,
+    eliminations: Vec<(Cell, ElementType)>,
+) -> Step {
+    let n = grid.elements();
+    Step {
+        eliminations,
+        pivot: Some(Cell::of(pivot, n)),
+        pincers: pincers.iter().map(|&i| Cell::of(i, n)).collect(),
+        elements: mask_elements(elements).collect(),
+        ..Step::new(technique)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::tests::grid_with;
+
+    #[test]
+    fn given_pivot_and_two_pincers_xy_wing_should_eliminate_z() {
+        let grid = grid_with(&[(0, 0, &[1, 2]), (0, 4, &[1, 3]), (2, 1, &[2, 3])]);
+        let step = xy_wing(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "XY-Wing: 1,2,3 pivot r1c1 pincers r1c5 r3c2 => \
+             r1c2<>3, r1c3<>3, r3c4<>3, r3c5<>3, r3c6<>3"
+        );
+    }
+
+    #[test]
+    fn given_trivalue_pivot_xyz_wing_should_eliminate_z_seeing_all_three() {
+        let grid = grid_with(&[(0, 0, &[1, 2, 3]), (0, 4, &[1, 3]), (2, 1, &[2, 3])]);
+        assert!(xy_wing(&grid).is_none());
+        let step = xyz_wing(&grid).unwrap();
+        assert_eq!(step.technique, Technique::XYZWing);
+        assert_eq!(step.pivot, Some(Cell { row: 0, column: 0 }));
+        assert_eq!(
+            step.eliminations,
+            vec![(Cell { row: 0, column: 1 }, 3), (Cell { row: 0, column: 2 }, 3)]
+        );
+    }
+
+    #[test]
+    fn given_two_pairs_linked_by_strong_link_w_wing_should_eliminate_other() {
+        // r1c1 and r5c5 are 1 or 2; in row 9 the 1 only fits in c1 and c5.
+        let mut cells: Vec<(SizeType, SizeType, &[ElementType])> = vec![(0, 0, &[1, 2]), (4, 4, &[1, 2])];
+        for column in [1, 2, 3, 5, 6, 7, 8] {
+            cells.push((8, column, &[2, 3, 4, 5, 6, 7, 8, 9]));
+        }
+        let grid = grid_with(&cells);
+        let step = w_wing(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "W-Wing: 1,2 pincers r1c1 r5c5 via r9c1 r9c5 (row 9) => r1c5<>2, r5c1<>2"
+        );
+    }
+}
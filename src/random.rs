@@ -0,0 +1,82 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! A small pseudo random number generator, so that a seed gives the
+//! same results on every machine and no dependency is needed.
+
+/// Pseudo random number generator using the SplitMix64 algorithm.
+/// Not suitable for anything where security matters.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    /// Returns the next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in the range `0..n`, which must not be empty.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "can't pick from an empty range.");
+        // Multiply and keep the high half; the bias is negligible for
+        // the small ranges used here.
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    /// Puts `items` in random order.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_same_seed_should_return_same_numbers() {
+        let mut a = Random::new(42);
+        let mut b = Random::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn given_seed_0_should_return_known_first_number() {
+        // Reference value of SplitMix64, guards against changes in
+        // the sequence that would change every generated puzzle.
+        assert_eq!(Random::new(0).next_u64(), 0xe220_a839_7b1d_cdaf);
+    }
+
+    #[test]
+    fn given_n_below_should_stay_below_n() {
+        let mut random = Random::new(7);
+        for n in 1..50 {
+            assert!(random.below(n) < n);
+        }
+    }
+
+    #[test]
+    fn given_items_shuffle_should_keep_the_same_items() {
+        let mut random = Random::new(7);
+        let mut items: Vec<u32> = (0..20).collect();
+        random.shuffle(&mut items);
+        assert_ne!(items, (0..20).collect::<Vec<_>>());
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<_>>());
+    }
+}
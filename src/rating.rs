@@ -0,0 +1,244 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Rating how hard a puzzle is by the hardest technique the logical
+//! solver needs for it. The numeric score follows Sudoku Explainer and
+//! the category follows HoDoKu, as far as their techniques match ours.
+//! The two don't always agree, so the hardest technique by score isn't
+//! always the one with the highest category.
+
+use std::fmt;
+
+use crate::logic::{self, LogicOptions, Technique};
+use crate::{SudokuError, SudokuGrid};
+
+/// How hard a puzzle is, in words.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    /// Singles only.
+    Easy,
+    /// Locked candidates, pairs and triples.
+    Medium,
+    /// X-Wings, Swordfish, quads, wings and uniqueness.
+    Hard,
+    /// Jellyfish, finned fish, chains and almost locked sets.
+    Unfair,
+    /// Beyond what the logical solver knows.
+    Extreme,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Category::Easy => "Easy",
+            Category::Medium => "Medium",
+            Category::Hard => "Hard",
+            Category::Unfair => "Unfair",
+            Category::Extreme => "Extreme",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Technique {
+    /// Difficulty of the technique on Sudoku Explainer's scale, roughly
+    /// from 1 to 10. Techniques it doesn't know are placed by how hard
+    /// they are compared to those it does: finned and sashimi fish score
+    /// 0.2 and 0.3 above the plain fish of the same size.
+    pub fn score(self) -> f32 {
+        match self {
+            Technique::HiddenSingle => 1.5,
+            Technique::NakedSingle => 2.3,
+            Technique::LockedCandidatesPointing => 2.6,
+            Technique::LockedCandidatesClaiming => 2.8,
+            Technique::NakedPair => 3.0,
+            Technique::XWing => 3.2,
+            Technique::HiddenPair => 3.4,
+            Technique::NakedTriple => 3.6,
+            Technique::Swordfish => 3.8,
+            Technique::HiddenTriple => 4.0,
+            Technique::XYWing => 4.2,
+            Technique::XYZWing => 4.4,
+            Technique::UniqueRectangle1 => 4.5,
+            Technique::UniqueRectangle2
+            | Technique::UniqueRectangle4
+            | Technique::UniqueRectangle5
+            | Technique::UniqueRectangle6
+            | Technique::HiddenRectangle => 4.6,
+            Technique::UniqueRectangle3 => 4.8,
+            Technique::WWing => 4.4,
+            Technique::FinnedXWing => 3.4,
+            Technique::SashimiXWing => 3.5,
+            Technique::NakedQuad => 5.0,
+            Technique::Jellyfish => 5.2,
+            Technique::HiddenQuad => 5.4,
+            Technique::BugPlusOne => 5.6,
+            Technique::FinnedSwordfish => 4.0,
+            Technique::SashimiSwordfish => 4.1,
+            Technique::FinnedJellyfish => 5.4,
+            Technique::SashimiJellyfish => 5.5,
+            Technique::XChain => 6.6,
+            Technique::XYChain => 6.6,
+            Technique::AlternatingInferenceChain => 7.0,
+            Technique::GroupedAlternatingInferenceChain => 7.3,
+            Technique::AlsXz => 7.5,
+            Technique::AlsXyWing => 8.0,
+            Technique::DeathBlossom => 8.5,
+        }
+    }
+
+    /// The category of puzzles needing the technique, as in HoDoKu.
+    pub fn category(self) -> Category {
+        match self {
+            Technique::HiddenSingle | Technique::NakedSingle => Category::Easy,
+            Technique::LockedCandidatesPointing
+            | Technique::LockedCandidatesClaiming
+            | Technique::NakedPair
+            | Technique::HiddenPair
+            | Technique::NakedTriple
+            | Technique::HiddenTriple => Category::Medium,
+            Technique::XWing
+            | Technique::Swordfish
+            | Technique::XYWing
+            | Technique::XYZWing
+            | Technique::UniqueRectangle1
+            | Technique::UniqueRectangle2
+            | Technique::UniqueRectangle3
+            | Technique::UniqueRectangle4
+            | Technique::UniqueRectangle5
+            | Technique::UniqueRectangle6
+            | Technique::HiddenRectangle
+            | Technique::WWing
+            | Technique::NakedQuad
+            | Technique::HiddenQuad
+            | Technique::BugPlusOne => Category::Hard,
+            Technique::Jellyfish
+            | Technique::FinnedXWing
+            | Technique::SashimiXWing
+            | Technique::FinnedSwordfish
+            | Technique::SashimiSwordfish
+            | Technique::FinnedJellyfish
+            | Technique::SashimiJellyfish
+            | Technique::XChain
+            | Technique::XYChain
+            | Technique::AlternatingInferenceChain
+            | Technique::GroupedAlternatingInferenceChain
+            | Technique::AlsXz
+            | Technique::AlsXyWing
+            | Technique::DeathBlossom => Category::Unfair,
+        }
+    }
+}
+
+/// How hard a puzzle is, and what it took to solve it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rating {
+    /// Score of the hardest technique needed, or `None` if the logical
+    /// solver couldn't finish the puzzle.
+    pub score: Option<f32>,
+
+    /// Highest category of the techniques needed, `Extreme` if the
+    /// logical solver couldn't finish the puzzle.
+    pub category: Category,
+
+    /// The hardest technique used, if any.
+    pub hardest: Option<Technique>,
+
+    /// How many steps used each technique, in the order of
+    /// `Technique::ALL`, leaving out those not used.
+    pub histogram: Vec<(Technique, usize)>,
+}
+
+/// Rates `grid` by solving it logically.
+pub fn rate(grid: &SudokuGrid) -> Result<Rating, SudokuError> {
+    rate_with_options(grid, LogicOptions::default())
+}
+
+/// Like `rate`, but lets the caller choose which techniques to use.
+pub fn rate_with_options(grid: &SudokuGrid, options: LogicOptions) -> Result<Rating, SudokuError> {
+    let solution = logic::solve_with_options(grid, options)?;
+    let histogram: Vec<(Technique, usize)> = Technique::ALL
+        .iter()
+        .map(|&technique| {
+            let count = solution.steps.iter().filter(|step| step.technique == technique).count();
+            (technique, count)
+        })
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    Ok(rating_of(histogram, solution.is_solved()))
+}
+
+/// The rating of a puzzle that took the steps counted in `histogram`,
+/// and was `solved` by them or not.
+fn rating_of(histogram: Vec<(Technique, usize)>, solved: bool) -> Rating {
+    let hardest = histogram
+        .iter()
+        .map(|&(technique, _)| technique)
+        .max_by(|a, b| a.score().total_cmp(&b.score()));
+    let category = histogram.iter().map(|&(technique, _)| technique.category()).max();
+    Rating {
+        score: match solved {
+            true => Some(hardest.map_or(0.0, Technique::score)),
+            false => None,
+        },
+        category: match solved {
+            true => category.unwrap_or(Category::Easy),
+            false => Category::Extreme,
+        },
+        hardest,
+        histogram,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn given_easy_sample_rate_should_say_easy() {
+        let grid = parse(include_str!("../samples/easy.sudoku")).unwrap();
+        let rating = rate(&grid).unwrap();
+        assert_eq!(Category::Easy, rating.category);
+        assert!(rating.score.unwrap() <= Technique::NakedSingle.score());
+        let steps: usize = rating.histogram.iter().map(|&(_, count)| count).sum();
+        assert_eq!(grid.cells.iter().filter(|&&x| x == 0).count(), steps);
+    }
+
+    #[test]
+    fn given_very_hard_sample_rate_should_score_hardest_technique() {
+        let grid = parse(include_str!("../samples/very-hard.sudoku")).unwrap();
+        let rating = rate(&grid).unwrap();
+        let hardest = rating.hardest.unwrap();
+        assert_eq!(Some(hardest.score()), rating.score);
+        assert_eq!(hardest.category(), rating.category);
+        assert!(rating.histogram.iter().all(|&(t, _)| t.score() <= hardest.score()));
+        assert_eq!(Category::Unfair, rating.category);
+    }
+
+    #[test]
+    fn given_mixed_histogram_rating_should_take_highest_category() {
+        let histogram = vec![(Technique::XYZWing, 2), (Technique::FinnedSwordfish, 1)];
+        let rating = rating_of(histogram, true);
+        assert_eq!(Category::Unfair, rating.category);
+        assert_eq!(Some(Technique::XYZWing), rating.hardest);
+        assert_eq!(Some(Technique::XYZWing.score()), rating.score);
+    }
+
+    #[test]
+    fn given_puzzle_beyond_logical_solver_rate_should_say_extreme() {
+        let grid = parse(include_str!("../samples/kind-of-hard.sudoku")).unwrap();
+        let rating = rate(&grid).unwrap();
+        assert_eq!(None, rating.score);
+        assert_eq!(Category::Extreme, rating.category);
+    }
+
+    #[test]
+    fn given_full_grid_rate_should_say_easy_with_no_steps() {
+        let grid = crate::generate::generate_grid(4, 1).unwrap();
+        let rating = rate(&grid).unwrap();
+        assert_eq!(Some(0.0), rating.score);
+        assert_eq!(Category::Easy, rating.category);
+        assert!(rating.histogram.is_empty());
+    }
+}
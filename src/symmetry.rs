@@ -0,0 +1,225 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Symmetries of the pattern of givens in a grid. Only which cells are
+//! given matters, not their values, as is usual for published puzzles.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{SizeType, SudokuError, SudokuGrid};
+
+/// Maps a row and column to another row and column, given the
+/// number of rows.
+type Transformation = fn(SizeType, SizeType, SizeType) -> (SizeType, SizeType);
+
+/// A symmetry that the pattern of givens may have.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symmetry {
+    /// Any pattern.
+    #[default]
+    None,
+
+    /// Unchanged when turned half a turn.
+    Rotational,
+
+    /// Unchanged when turned a quarter turn, and so also half a turn.
+    QuarterTurn,
+
+    /// Top half is a mirror image of the bottom half.
+    Horizontal,
+
+    /// Left half is a mirror image of the right half.
+    Vertical,
+
+    /// Unchanged when mirrored in the diagonal from top-left to
+    /// bottom-right.
+    Diagonal,
+
+    /// Unchanged when mirrored in the diagonal from top-right to
+    /// bottom-left.
+    AntiDiagonal,
+
+    /// Unchanged by any turn or mirroring of the square, so all of
+    /// the above at once.
+    Dihedral,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::None,
+        Symmetry::Rotational,
+        Symmetry::QuarterTurn,
+        Symmetry::Horizontal,
+        Symmetry::Vertical,
+        Symmetry::Diagonal,
+        Symmetry::AntiDiagonal,
+        Symmetry::Dihedral,
+    ];
+
+    /// The transformations that must leave the pattern unchanged.
+    fn transformations(self) -> &'static [Transformation] {
+        match self {
+            Symmetry::None => &[],
+            Symmetry::Rotational => &[half_turn],
+            Symmetry::QuarterTurn => &[quarter_turn, half_turn, three_quarter_turn],
+            Symmetry::Horizontal => &[mirror_horizontal],
+            Symmetry::Vertical => &[mirror_vertical],
+            Symmetry::Diagonal => &[mirror_diagonal],
+            Symmetry::AntiDiagonal => &[mirror_anti_diagonal],
+            Symmetry::Dihedral => &[
+                quarter_turn,
+                half_turn,
+                three_quarter_turn,
+                mirror_horizontal,
+                mirror_vertical,
+                mirror_diagonal,
+                mirror_anti_diagonal,
+            ],
+        }
+    }
+
+    /// Returns the cells that have to be given or empty together with
+    /// the cell at `index` in a grid with `n` rows, including `index`.
+    pub fn orbit(self, n: SizeType, index: SizeType) -> Vec<SizeType> {
+        let (row, col) = (index / n, index % n);
+        let mut orbit = vec![index];
+        for transform in self.transformations() {
+            let (r, c) = transform(n, row, col);
+            let image = r * n + c;
+            if !orbit.contains(&image) {
+                orbit.push(image);
+            }
+        }
+        orbit
+    }
+}
+
+fn quarter_turn(n: SizeType, row: SizeType, col: SizeType) -> (SizeType, SizeType) {
+    (col, n - 1 - row)
+}
+
+fn half_turn(n: SizeType, row: SizeType, col: SizeType) -> (SizeType, SizeType) {
+    (n - 1 - row, n - 1 - col)
+}
+
+fn three_quarter_turn(n: SizeType, row: SizeType, col: SizeType) -> (SizeType, SizeType) {
+    (n - 1 - col, row)
+}
+
+fn mirror_horizontal(n: SizeType, row: SizeType, col: SizeType) -> (SizeType, SizeType) {
+    (n - 1 - row, col)
+}
+
+fn mirror_vertical(n: SizeType, row: SizeType, col: SizeType) -> (SizeType, SizeType) {
+    (row, n - 1 - col)
+}
+
+fn mirror_diagonal(_n: SizeType, row: SizeType, col: SizeType) -> (SizeType, SizeType) {
+    (col, row)
+}
+
+fn mirror_anti_diagonal(n: SizeType, row: SizeType, col: SizeType) -> (SizeType, SizeType) {
+    (n - 1 - col, n - 1 - row)
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Symmetry::None => "none",
+            Symmetry::Rotational => "rotational",
+            Symmetry::QuarterTurn => "quarter-turn",
+            Symmetry::Horizontal => "horizontal",
+            Symmetry::Vertical => "vertical",
+            Symmetry::Diagonal => "diagonal",
+            Symmetry::AntiDiagonal => "anti-diagonal",
+            Symmetry::Dihedral => "dihedral",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = SudokuError;
+    fn from_str(s: &str) -> Result<Symmetry, SudokuError> {
+        Symmetry::ALL
+            .iter()
+            .copied()
+            .find(|symmetry| symmetry.to_string() == s)
+            .ok_or_else(|| SudokuError::UnknownSymmetry(s.to_string()))
+    }
+}
+
+/// Returns the symmetries the pattern of givens in `grid` has.
+/// `Symmetry::None` is always included.
+pub fn symmetries_of(grid: &SudokuGrid) -> Vec<Symmetry> {
+    let n = grid.elements;
+    Symmetry::ALL
+        .iter()
+        .copied()
+        .filter(|symmetry| {
+            (0..grid.size).all(|index| {
+                let given = grid.is_given(index);
+                symmetry
+                    .orbit(n, index)
+                    .iter()
+                    .all(|&i| grid.is_given(i) == given)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn given_corner_of_9x9_dihedral_orbit_should_be_the_four_corners() {
+        let mut orbit = Symmetry::Dihedral.orbit(9, 0);
+        orbit.sort();
+        assert_eq!(orbit, vec![0, 8, 72, 80]);
+    }
+
+    #[test]
+    fn given_center_of_9x9_any_orbit_should_be_just_the_center() {
+        for symmetry in Symmetry::ALL {
+            assert_eq!(symmetry.orbit(9, 40), vec![40]);
+        }
+    }
+
+    #[test]
+    fn given_names_from_str_should_parse_what_display_writes() {
+        for symmetry in Symmetry::ALL {
+            assert_eq!(symmetry.to_string().parse::<Symmetry>(), Ok(symmetry));
+        }
+        assert!("sideways".parse::<Symmetry>().is_err());
+    }
+
+    #[test]
+    fn given_easy_sample_symmetries_of_should_report_vertical() {
+        let grid = parse(include_str!("../samples/easy.sudoku")).unwrap();
+        assert_eq!(symmetries_of(&grid), vec![Symmetry::None, Symmetry::Vertical]);
+    }
+
+    #[test]
+    fn given_solved_easy_sample_symmetries_of_should_look_only_at_givens() {
+        let grid = parse(include_str!("../samples/easy.sudoku")).unwrap();
+        let solved = crate::solutions(&grid).unwrap().next().unwrap();
+        assert_eq!(symmetries_of(&solved), vec![Symmetry::None, Symmetry::Vertical]);
+    }
+
+    #[test]
+    fn given_4x4_grid_with_diagonal_givens_symmetries_of_should_report_diagonal() {
+        let grid = parse("1... .2.. ..3. ...4").unwrap();
+        assert_eq!(
+            symmetries_of(&grid),
+            vec![
+                Symmetry::None,
+                Symmetry::Rotational,
+                Symmetry::Diagonal,
+                Symmetry::AntiDiagonal,
+            ]
+        );
+    }
+}
@@ -0,0 +1,129 @@
+// © Copyright 2021 Sudoku Rustic’s Authors
+// Subject to the MIT License. See file LICENSE for details.
+
+//! Houses a puzzle may have besides rows, columns and boxes, where each
+//! element must also appear exactly once. Each kind makes a well-known
+//! variant of sudoku.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{House, SizeType, SudokuError, SudokuGrid};
+
+/// A kind of extra houses that can be added to a grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtraHouses {
+    /// Both main diagonals, as in Sudoku-X. The first goes from the
+    /// top-left to the bottom-right cell.
+    Diagonals,
+
+    /// Windows the shape of a box, a cell in from the edges and a cell
+    /// apart, as in Windoku or Hyper sudoku: four of them in a 9x9 grid.
+    Windows,
+
+    /// Disjoint groups: the cells at the same place within every box.
+    DisjointGroups,
+}
+
+impl ExtraHouses {
+    pub const ALL: [ExtraHouses; 3] = [ExtraHouses::Diagonals, ExtraHouses::Windows, ExtraHouses::DisjointGroups];
+
+    /// True if a grid with irregular regions can have these, which
+    /// have no boxes for windows and disjoint groups to follow.
+    pub(crate) fn fit_regions(self) -> bool {
+        self == ExtraHouses::Diagonals
+    }
+
+    /// Returns what each of these houses in `grid` is, with its cells.
+    pub(crate) fn houses(self, grid: &SudokuGrid) -> Vec<(House, Vec<SizeType>)> {
+        let n = grid.elements;
+        let (width, height) = (grid.box_width, grid.box_height);
+        match self {
+            ExtraHouses::Diagonals => vec![
+                (House::Diagonal(0), (0..n).map(|i| i * n + i).collect()),
+                (House::Diagonal(1), (0..n).map(|i| i * n + n - 1 - i).collect()),
+            ],
+            ExtraHouses::Windows => {
+                // Top and left lines of the windows.
+                let rows: Vec<SizeType> = (1..n).step_by(height + 1).filter(|&r| r + height <= n).collect();
+                let cols: Vec<SizeType> = (1..n).step_by(width + 1).filter(|&c| c + width <= n).collect();
+                let corners = rows.iter().flat_map(|&r| cols.iter().map(move |&c| r * n + c));
+                corners
+                    .enumerate()
+                    .map(|(w, corner)| {
+                        let cells = (0..n).map(|i| corner + (i / width) * n + i % width).collect();
+                        (House::Window(w), cells)
+                    })
+                    .collect()
+            }
+            ExtraHouses::DisjointGroups => (0..n)
+                .map(|g| {
+                    let cells = (0..grid.size)
+                        .filter(|&i| (i / n % height) * width + i % n % width == g)
+                        .collect();
+                    (House::Group(g), cells)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for ExtraHouses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ExtraHouses::Diagonals => "diagonals",
+            ExtraHouses::Windows => "windows",
+            ExtraHouses::DisjointGroups => "disjoint-groups",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for ExtraHouses {
+    type Err = SudokuError;
+    fn from_str(s: &str) -> Result<ExtraHouses, SudokuError> {
+        ExtraHouses::ALL
+            .iter()
+            .copied()
+            .find(|extra| extra.to_string() == s)
+            .ok_or_else(|| SudokuError::UnknownExtraHouses(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells_of(extra: ExtraHouses, grid: &SudokuGrid) -> Vec<Vec<SizeType>> {
+        extra.houses(grid).into_iter().map(|(_, cells)| cells).collect()
+    }
+
+    #[test]
+    fn given_9x9_grid_windows_should_be_four_boxes_a_cell_in() {
+        let windows = cells_of(ExtraHouses::Windows, &SudokuGrid::new(9).unwrap());
+        assert_eq!(vec![10, 14, 46, 50], windows.iter().map(|w| w[0]).collect::<Vec<_>>());
+        assert_eq!(vec![10, 11, 12, 19, 20, 21, 28, 29, 30], windows[0]);
+    }
+
+    #[test]
+    fn given_6x6_grid_disjoint_groups_should_follow_3x2_boxes() {
+        let groups = cells_of(ExtraHouses::DisjointGroups, &SudokuGrid::new(6).unwrap());
+        assert_eq!(6, groups.len());
+        assert_eq!(vec![0, 3, 12, 15, 24, 27], groups[0]);
+        assert_eq!(vec![8, 11, 20, 23, 32, 35], groups[5]);
+    }
+
+    #[test]
+    fn given_4x4_grid_diagonals_should_cross_the_grid() {
+        let diagonals = cells_of(ExtraHouses::Diagonals, &SudokuGrid::new(4).unwrap());
+        assert_eq!(vec![vec![0, 5, 10, 15], vec![3, 6, 9, 12]], diagonals);
+    }
+
+    #[test]
+    fn given_names_from_str_should_parse_what_display_writes() {
+        for extra in ExtraHouses::ALL {
+            assert_eq!(extra.to_string().parse::<ExtraHouses>(), Ok(extra));
+        }
+        assert_eq!(Err(SudokuError::UnknownExtraHouses("hyper".to_string())), "hyper".parse::<ExtraHouses>());
+    }
+}